@@ -0,0 +1,188 @@
+// heap allocations and write syscalls per frame on the write path, run with `cargo bench`.
+// the uinput cases need access to /dev/uinput and are skipped without it
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs::{self, File};
+use std::hint::black_box;
+use std::io::Read;
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mouse_keyboard_input::key_codes::*;
+use mouse_keyboard_input::*;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const WARM_UP: usize = 100;
+const CALLS: usize = 10_000;
+
+// write-family syscalls of the whole process, the pipe reader only reads
+fn write_syscalls() -> u64 {
+    let io = fs::read_to_string("/proc/self/io").expect("task io accounting is required");
+    io.lines()
+        .find_map(|line| line.strip_prefix("syscw: "))
+        .and_then(|count| count.parse().ok())
+        .unwrap()
+}
+
+struct Counters {
+    allocations: u64,
+    syscalls: u64,
+    started: Instant,
+}
+
+impl Counters {
+    fn start() -> Self {
+        Self {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            syscalls: write_syscalls(),
+            started: Instant::now(),
+        }
+    }
+
+    fn report(self, name: &str, frames: usize) {
+        let elapsed = self.started.elapsed();
+        // reading /proc allocates, so the allocations are taken first
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - self.allocations;
+        let syscalls = write_syscalls() - self.syscalls;
+        let frames = frames as f64;
+        println!(
+            "{name:<48} {:>7.3} allocs/frame {:>7.3} writes/frame {:>9.0} ns/frame",
+            allocations as f64 / frames,
+            syscalls as f64 / frames,
+            elapsed.as_nanos() as f64 / frames,
+        );
+    }
+}
+
+// `call` writes `frames_per_call` frames
+fn measure(name: &str, frames_per_call: usize, mut call: impl FnMut(usize)) {
+    for idx in 0..WARM_UP {
+        call(idx);
+    }
+    let counters = Counters::start();
+    for idx in 0..CALLS {
+        call(idx);
+    }
+    counters.report(name, CALLS * frames_per_call);
+}
+
+// alternates the direction so the pointer stays where it is
+fn wiggle(idx: usize) -> Coord {
+    if idx.is_multiple_of(2) { 1 } else { -1 }
+}
+
+fn drained_pipe() -> OwnedFd {
+    let mut fds = [0; 2];
+    unsafe {
+        assert_eq!(libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC), 0);
+        let mut reader = File::from_raw_fd(fds[0]);
+        libc::fcntl(fds[0], libc::F_SETFL, 0);
+
+        thread::spawn(move || {
+            let mut chunk = [0; 1 << 16];
+            while reader.read(&mut chunk).is_ok_and(|count| count > 0) {}
+        });
+        OwnedFd::from_raw_fd(fds[1])
+    }
+}
+
+fn bench_pipe() {
+    let mut writer = EventWriter::new(drained_pipe()).with_timeout(Duration::from_secs(1));
+
+    measure("EventWriter: motion frame", 1, |idx| {
+        let batch = [(EV_REL, REL_X, wiggle(idx)), (EV_REL, REL_Y, 0), (EV_SYN, SYN_REPORT, 0)];
+        writer.write_events(black_box(&batch)).unwrap();
+    });
+
+    let mut batch = Vec::new();
+    measure("EventWriter: 64 motion frames per write", 64, |idx| {
+        batch.clear();
+        for _ in 0..64 {
+            batch.extend([(EV_REL, REL_X, wiggle(idx)), (EV_SYN, SYN_REPORT, 0)]);
+        }
+        writer.write_events(black_box(&batch)).unwrap();
+    });
+}
+
+fn bench_uinput(mut device: VirtualDevice) {
+    measure("VirtualDevice::move_mouse", 1, |idx| {
+        device.move_mouse(wiggle(idx), 0).unwrap();
+    });
+
+    measure("VirtualDevice::press + release", 2, |_| {
+        device.press(KEY_F24).unwrap();
+        device.release(KEY_F24).unwrap();
+    });
+
+    measure("VirtualDevice::emit", 1, |idx| {
+        device.emit(&[InputEvent::Rel(RelAxis::X, wiggle(idx))]).unwrap();
+    });
+
+    let mut batch = Vec::new();
+    let frames = {
+        device.buffered_gradual_move_mouse_into(300, 20, &mut batch);
+        batch.iter().filter(|event| event.0 == EV_SYN).count()
+    };
+    measure("buffered_gradual_move_mouse_into + write_batch", frames, |idx| {
+        batch.clear();
+        let direction = wiggle(idx);
+        device.buffered_gradual_move_mouse_into(300 * direction, 20 * direction, &mut batch);
+        device.write_batch(&batch).unwrap();
+    });
+
+    // includes the sender side: every frame is a new `Frame` sent over the channel
+    let handle = device.handle();
+    let flush = device.flush_channel_blocking(Duration::from_micros(500));
+    for idx in 0..WARM_UP {
+        handle.move_mouse(wiggle(idx), 0).unwrap();
+    }
+    let counters = Counters::start();
+    for idx in 0..CALLS {
+        handle.move_mouse(wiggle(idx), 0).unwrap();
+    }
+    flush.stop().unwrap();
+    counters.report("DeviceHandle::move_mouse + flush thread", CALLS);
+}
+
+fn main() {
+    bench_pipe();
+
+    // `VirtualDevice::new` panics when the uinput module isn't loaded
+    if !Path::new("/dev/uinput").exists() {
+        println!("uinput cases skipped: /dev/uinput doesn't exist");
+        return;
+    }
+    match VirtualDevice::default() {
+        Ok(device) => bench_uinput(device),
+        Err(err) => println!("uinput cases skipped: {err}"),
+    }
+}
@@ -0,0 +1,58 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// typed names for the constants of key_codes.rs, see code_names.rs
+const TYPES: [(&str, &[&str]); 3] = [
+    ("Key", &["KEY_", "BTN_"]),
+    ("RelAxis", &["REL_"]),
+    ("AbsAxis", &["ABS_"]),
+];
+
+// bounds of a range, not codes of their own
+const SKIPPED: [&str; 3] = ["MAX", "CNT", "MIN_INTERESTING"];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/key_codes.rs");
+
+    let codes = fs::read_to_string("src/key_codes.rs").expect("src/key_codes.rs is readable");
+    // deprecated constants get no typed name
+    let mut deprecated = false;
+    let mut constants = Vec::new();
+    for line in codes.lines() {
+        if line.starts_with("#[deprecated") {
+            deprecated = true;
+        } else if let Some(constant) = line.strip_prefix("pub const ") {
+            if !deprecated {
+                constants.extend(constant.split(':').next().map(str::trim));
+            }
+            deprecated = false;
+        }
+    }
+
+    let mut out = String::new();
+    for (type_name, prefixes) in TYPES {
+        out += &format!("code_type!(impl {type_name} {{\n");
+        for constant in &constants {
+            let Some(prefix) = prefixes.iter().find(|prefix| constant.starts_with(*prefix)) else {
+                continue;
+            };
+            let short = &constant[prefix.len()..];
+            if SKIPPED.contains(&short) {
+                continue;
+            }
+            // buttons keep their prefix and an identifier can't start with a digit
+            let name = if *prefix == "BTN_" || short.starts_with(|c: char| c.is_ascii_digit()) {
+                constant
+            } else {
+                short
+            };
+            out += &format!("    {name} = {constant},\n");
+        }
+        out += "});\n";
+    }
+
+    let path = Path::new(&env::var("OUT_DIR").expect("cargo sets OUT_DIR")).join("code_names.rs");
+    fs::write(path, out).expect("OUT_DIR is writable");
+}
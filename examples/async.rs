@@ -0,0 +1,20 @@
+use mouse_keyboard_input::key_codes::*;
+use mouse_keyboard_input::*;
+use std::time::Duration;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let device = VirtualDevice::default().unwrap();
+    let mut device = AsyncVirtualDevice::new(device, &TokioRuntime).unwrap();
+
+    // the task can be spawned, nothing in it blocks the executor
+    tokio::spawn(async move {
+        device.move_mouse_over(200, 100, Duration::from_millis(300), Easing::EaseInOut).await.unwrap();
+        device.click(BTN_LEFT).await.unwrap();
+        device.type_text("Hello World!").await.unwrap();
+        // dropping the device would release held keys with a blocking write
+        device.close().await.unwrap();
+    })
+    .await
+    .unwrap();
+}
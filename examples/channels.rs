@@ -3,17 +3,17 @@ use std::thread;
 use std::thread::JoinHandle;
 use std::time::Duration;
 
-fn write_events_in_thread(sender: ChannelSender) -> JoinHandle<()> {
+fn write_events_in_thread(device: DeviceHandle) -> JoinHandle<()> {
     thread::spawn(move || {
         for _ in 1..5 {
             thread::sleep(Duration::from_secs(1));
 
             // scroll vertically by 100
-            VirtualDevice::send_scroll_y(100, &sender.clone()).unwrap();
+            device.scroll_y(100).unwrap();
             // move cursor vertically from the current position by 50
-            VirtualDevice::send_mouse_move(50, 50, &sender.clone()).unwrap();
+            device.move_mouse(50, 50).unwrap();
             //click the left mouse button
-            VirtualDevice::send_click(BTN_LEFT, &sender.clone()).unwrap();
+            device.click(BTN_LEFT).unwrap();
         };
     })
 }
@@ -21,9 +21,11 @@ fn write_events_in_thread(sender: ChannelSender) -> JoinHandle<()> {
 fn main() {
     let device = VirtualDevice::default().unwrap();
 
-    let sender = device.sender.clone();
+    let handle = device.handle();
 
-    device.flush_channel_every_interval();
+    let flush = device.flush_channel_every_interval_with(|err| eprintln!("{err}"));
 
-    write_events_in_thread(sender).join().unwrap();
-}
\ No newline at end of file
+    write_events_in_thread(handle).join().unwrap();
+
+    flush.stop().unwrap();
+}
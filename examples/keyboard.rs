@@ -1,4 +1,4 @@
-use mouse_keyboard_input::VirtualDevice;
+use mouse_keyboard_input::{TimingProfile, VirtualDevice};
 use mouse_keyboard_input::key_codes::*;
 use std::thread;
 use std::time::Duration;
@@ -12,4 +12,8 @@ fn main() {
     for key in [KEY_H, KEY_E, KEY_L, KEY_L, KEY_O] {
         device.click(key).unwrap();
     }
+
+    // type with human-like, reproducible timing
+    device.set_timing_profile(TimingProfile::human(42));
+    device.type_text(" World!").unwrap();
 }
\ No newline at end of file
@@ -0,0 +1,30 @@
+use mouse_keyboard_input::key_codes::*;
+use mouse_keyboard_input::*;
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let device = VirtualDevice::default().unwrap();
+
+    let mouse_keys = MouseKeys::new(MouseKeysSettings::default());
+    let input = mouse_keys.input();
+
+    let mouse_keys = mouse_keys.spawn(device.writing_interval(), device.handle()).unwrap();
+    let jiggler = spawn_jiggler(JigglerSettings::default(), device.handle()).unwrap();
+    let flush = device.flush_channel_every_interval();
+
+    // hold keypad 9 to move diagonally up and to the right, the pointer speeds up over time
+    input.handle_key(KEY_KP9, true);
+    thread::sleep(Duration::from_secs(2));
+    input.handle_key(KEY_KP9, false);
+
+    // keypad 5 clicks
+    input.handle_key(KEY_KP5, true);
+    input.handle_key(KEY_KP5, false);
+    thread::sleep(Duration::from_millis(100));
+
+    mouse_keys.stop();
+    jiggler.stop();
+    // writes the remaining events and releases held keys before destroying the device
+    flush.stop().unwrap();
+}
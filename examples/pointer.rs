@@ -0,0 +1,27 @@
+use mouse_keyboard_input::*;
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let device = VirtualDevice::default().unwrap();
+
+    let controller = PointerController::new(PointerSettings::default());
+    let input = controller.input();
+
+    let pointer = controller.spawn(device.writing_interval(), device.handle()).unwrap();
+    let flush = device.flush_channel_every_interval();
+
+    // pretend the stick is pushed half way to the right and then fully up
+    input.set(0.5, 0.0);
+    thread::sleep(Duration::from_secs(1));
+
+    input.set(0.0, 1.0);
+    thread::sleep(Duration::from_secs(1));
+
+    input.set(0.0, 0.0);
+    thread::sleep(Duration::from_millis(100));
+
+    pointer.stop();
+    // writes the remaining events and releases held keys before destroying the device
+    flush.stop().unwrap();
+}
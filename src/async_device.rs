@@ -0,0 +1,445 @@
+use std::future::{Future, poll_fn};
+use std::io;
+use std::os::fd::{AsRawFd, RawFd};
+use std::pin::pin;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use crate::key_codes::*;
+use crate::virtual_device::plan_keystrokes;
+use crate::writer::{EVENT_SIZE, EventBuffer, overdue_end, write_rest, write_some};
+use crate::{Button, Coord, Easing, EmptyResult, EventParams, MotionFrames, RelAxis, SYN_PARAMS, VirtualDevice, WriteError};
+
+// the two things the device needs from an executor: fd readiness and timers
+pub trait AsyncRuntime {
+    // the uinput fd registered with the reactor, dropped before the device closes the fd
+    type Registration: Send + Sync;
+
+    /// # Safety
+    ///
+    /// `fd` must be an open file descriptor that stays open, and isn't reused for another file,
+    /// until the returned registration is dropped.
+    unsafe fn register(&self, fd: RawFd) -> io::Result<Self::Registration>;
+
+    // resolves once the fd may accept writes again
+    fn writable(registration: &Self::Registration) -> impl Future<Output = io::Result<()>> + Send;
+
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+#[cfg(feature = "tokio")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioRuntime;
+
+#[cfg(feature = "tokio")]
+impl AsyncRuntime for TokioRuntime {
+    type Registration = tokio::io::unix::AsyncFd<RawFd>;
+
+    unsafe fn register(&self, fd: RawFd) -> io::Result<Self::Registration> {
+        // the caller keeps the fd open while it's registered
+        unsafe { Ok(tokio::io::unix::AsyncFd::register_with_interest(fd, tokio::io::Interest::WRITABLE)?) }
+    }
+
+    async fn writable(registration: &Self::Registration) -> io::Result<()> {
+        // the next write decides whether the fd is really writable
+        registration.writable().await?.clear_ready();
+        Ok(())
+    }
+
+    async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+}
+
+// same device, but writes wait for the fd instead of failing with EAGAIN
+// and delays are runtime timers instead of `thread::sleep`.
+// dropping it releases held keys with the blocking write of `VirtualDevice`, which can hold
+// the executor thread for up to `write_timeout`, `close` releases them through the runtime instead
+pub struct AsyncVirtualDevice<R: AsyncRuntime> {
+    // declared first so it is deregistered before the device closes the fd
+    registration: R::Registration,
+    device: VirtualDevice,
+    // the last batch, of which `written` bytes reached the fd and `tracked` events were tracked.
+    // a write that was cancelled halfway leaves the rest there until the next write finishes it
+    buffer: EventBuffer,
+    written: usize,
+    tracked: usize,
+}
+
+impl<R: AsyncRuntime> AsyncVirtualDevice<R> {
+    pub fn new(device: VirtualDevice, runtime: &R) -> io::Result<Self> {
+        Ok(Self {
+            // the registration is dropped before the device, so the fd stays open while it's registered
+            registration: unsafe { runtime.register(device.file().as_raw_fd())? },
+            device,
+            buffer: EventBuffer::default(),
+            written: 0,
+            tracked: 0,
+        })
+    }
+
+    #[inline]
+    pub fn device(&self) -> &VirtualDevice {
+        &self.device
+    }
+
+    // blocking methods of the device still block the executor.
+    // a cancelled write is finished first with the blocking writer, or given up if that fails
+    pub fn device_mut(&mut self) -> &mut VirtualDevice {
+        let _ = self.finish_blocking();
+        &mut self.device
+    }
+
+    // the whole batch is written even if the fd only takes part of it at a time. gives up with
+    // `WriteError::Timeout` after the device's `write_timeout`, like the blocking writes.
+    // cancel-safe: if the future is dropped halfway, the next write or the drop of the device
+    // finishes the batch first, and events that reached the fd are tracked before every wait
+    pub async fn write_batch(&mut self, batch: &[EventParams]) -> EmptyResult {
+        self.finish_write().await?;
+        self.buffer.encode(batch);
+        self.written = 0;
+        self.tracked = 0;
+        self.finish_write().await
+    }
+
+    // writes the rest of the batch in `buffer`
+    async fn finish_write(&mut self) -> EmptyResult {
+        let len = self.buffer.bytes().len();
+        if self.written == len {
+            return Ok(());
+        }
+        let deadline = Instant::now() + self.device.write_timeout();
+        // once the deadline has passed only the torn event is finished, see `write_whole`
+        let mut end = len;
+
+        let result = loop {
+            match write_some(self.device.file(), &self.buffer.bytes()[..end], &mut self.written) {
+                Ok(true) if end == len => break Ok(()),
+                Ok(true) => break Err(WriteError::Timeout { committed: self.written / EVENT_SIZE }),
+                Ok(false) => {}
+                Err(error) => break Err(WriteError::Io { committed: self.written / EVENT_SIZE, error }),
+            }
+            self.track_written();
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if end == len && remaining.is_zero() {
+                match overdue_end(self.written) {
+                    Some(torn_end) => end = torn_end,
+                    None => break Err(WriteError::Timeout { committed: self.written / EVENT_SIZE }),
+                }
+            }
+            let writable = if end == len {
+                // `None` once the deadline passes, the next round gives up
+                within::<R, _>(remaining, R::writable(&self.registration)).await.unwrap_or(Ok(()))
+            } else {
+                R::writable(&self.registration).await
+            };
+            if let Err(error) = writable {
+                break Err(WriteError::Io { committed: self.written / EVENT_SIZE, error });
+            }
+        };
+
+        self.track_written();
+        if result.is_err() {
+            self.give_up_write();
+        }
+        result.map_err(|err| Box::new(err) as _)
+    }
+
+    // only what reached the kernel counts as pressed or moved
+    fn track_written(&mut self) {
+        let committed = self.written / EVENT_SIZE;
+        for idx in self.tracked..committed {
+            self.device.track_batch(&[self.buffer.event(idx)]);
+        }
+        self.tracked = self.tracked.max(committed);
+    }
+
+    // the rest of a failed batch is never written
+    #[inline]
+    fn give_up_write(&mut self) {
+        self.written = self.buffer.bytes().len();
+        self.tracked = self.buffer.len();
+    }
+
+    fn finish_blocking(&mut self) -> EmptyResult {
+        let result = write_rest(self.device.file(), self.buffer.bytes(), &mut self.written, self.device.write_timeout());
+        self.track_written();
+        if result.is_err() {
+            self.give_up_write();
+        }
+        Ok(result?)
+    }
+
+    pub async fn press(&mut self, button: impl Into<Button>) -> EmptyResult {
+        let button = button.into();
+        self.device.ensure_key(button)?;
+        self.write_batch(&[(EV_KEY, button.code(), 1), SYN_PARAMS]).await
+    }
+
+    pub async fn release(&mut self, button: impl Into<Button>) -> EmptyResult {
+        let button = button.into();
+        self.device.ensure_key(button)?;
+        self.write_batch(&[(EV_KEY, button.code(), 0), SYN_PARAMS]).await
+    }
+
+    // holds for `TimingProfile::hold` of the device
+    pub async fn click(&mut self, button: impl Into<Button>) -> EmptyResult {
+        let hold = self.device.timing_mut().hold();
+        self.click_for(button, hold).await
+    }
+
+    // not cancel-safe: dropped while it holds the button, the button stays pressed until
+    // it is released, `release_all` or `close` is awaited, or the device is dropped.
+    // the same goes for `click` and `type_text`
+    pub async fn click_for(&mut self, button: impl Into<Button>, hold: Duration) -> EmptyResult {
+        let button = button.into();
+        self.press(button).await?;
+        R::sleep(hold).await;
+        self.release(button).await
+    }
+
+    pub async fn type_text(&mut self, text: &str) -> EmptyResult {
+        let keystrokes = plan_keystrokes(text, self.device.timing_mut())?;
+        for keystroke in keystrokes {
+            R::sleep(keystroke.delay).await;
+            if keystroke.shift {
+                self.press(KEY_LEFTSHIFT).await?;
+            }
+            // the error isn't `Send`, so only its message is kept while Shift is released
+            let failed = self.click_for(keystroke.key, keystroke.hold).await.err().map(|err| err.to_string());
+            if keystroke.shift {
+                self.release(KEY_LEFTSHIFT).await?;
+            }
+            if let Some(message) = failed {
+                return Err(Box::from(message));
+            }
+        }
+        Ok(())
+    }
+
+    // in reverse order of their codes, like `VirtualDevice::release_all`
+    pub async fn release_all(&mut self) -> EmptyResult {
+        let pressed = self.device.pressed_keys();
+        if pressed.is_empty() {
+            return Ok(());
+        }
+
+        let mut batch: Vec<EventParams> = pressed.iter().rev().map(|key| (EV_KEY, key.code(), 0)).collect();
+        batch.push(SYN_PARAMS);
+        self.write_batch(&batch).await
+    }
+
+    // releases held keys without blocking the executor and destroys the device.
+    // if that fails, dropping the device still tries the blocking release
+    pub async fn close(mut self) -> EmptyResult {
+        self.release_all().await
+    }
+
+    pub async fn move_mouse(&mut self, x: Coord, y: Coord) -> EmptyResult {
+        self.device.ensure_rel(RelAxis::X)?;
+        self.device.ensure_rel(RelAxis::Y)?;
+        let (x, y) = self.device.map_motion(x as f64, y as f64);
+        self.write_nonzero([(EV_REL, REL_X, x), (EV_REL, REL_Y, y)]).await
+    }
+
+    // stops a running fling like every other scroll
+    pub async fn scroll(&mut self, x: Coord, y: Coord) -> EmptyResult {
+        self.device.ensure_scroll()?;
+        self.device.cancel_fling();
+        let [hwheel, wheel, notches_x, notches_y] = self.device.scroll_events(x as f64, y as f64);
+        self.write_nonzero([hwheel, wheel, notches_x, notches_y]).await
+    }
+
+    // same rule as the blocking device: axes that don't move are left out, nothing moving writes nothing
+    async fn write_nonzero<const N: usize>(&mut self, events: [EventParams; N]) -> EmptyResult {
+        let mut batch = [SYN_PARAMS; 5];
+        let mut count = 0;
+        for event in events.into_iter().filter(|event| event.2 != 0) {
+            batch[count] = event;
+            count += 1;
+        }
+        if count == 0 {
+            return Ok(());
+        }
+        self.write_batch(&batch[..=count]).await
+    }
+
+    pub async fn move_mouse_over(&mut self, x: Coord, y: Coord, duration: Duration, easing: Easing) -> EmptyResult {
+        let frames = MotionFrames::new(x, y, duration, easing, self.device.motion_rate());
+        self.play_motion(frames).await
+    }
+
+    pub async fn move_mouse_human(&mut self, x: Coord, y: Coord) -> EmptyResult {
+        let trajectory = self.device.human_trajectory(x, y);
+        self.play_motion(trajectory).await
+    }
+
+    // frames are (offset from now, dx, dy)
+    pub async fn play_motion(&mut self, frames: impl IntoIterator<Item = (Duration, Coord, Coord)>) -> EmptyResult {
+        let start = Instant::now();
+
+        for (offset, dx, dy) in frames {
+            if let Some(remaining) = (start + offset).checked_duration_since(Instant::now()) {
+                R::sleep(remaining).await;
+            }
+            self.move_mouse(dx, dy).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: AsyncRuntime> Drop for AsyncVirtualDevice<R> {
+    fn drop(&mut self) {
+        // a cancelled write goes out before the device writes the releases behind it
+        let _ = self.finish_blocking();
+    }
+}
+
+// `None` if `duration` passes before `future` resolves
+async fn within<R: AsyncRuntime, F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    let mut future = pin!(future);
+    let mut timer = pin!(R::sleep(duration));
+    poll_fn(|cx| match future.as_mut().poll(cx) {
+        Poll::Ready(output) => Poll::Ready(Some(output)),
+        Poll::Pending => timer.as_mut().poll(cx).map(|()| None),
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Key;
+    use crate::virtual_device::tests::{drain, handle_device, pipe_device, presses, read_events};
+
+    // never reports the fd as writable, so a full pipe ends the write like a runtime that gave up
+    struct TimedOutRuntime;
+
+    impl AsyncRuntime for TimedOutRuntime {
+        type Registration = ();
+
+        unsafe fn register(&self, _fd: RawFd) -> io::Result<()> {
+            Ok(())
+        }
+
+        async fn writable(_registration: &()) -> io::Result<()> {
+            Err(io::ErrorKind::TimedOut.into())
+        }
+
+        async fn sleep(_duration: Duration) {}
+    }
+
+    // the fd never becomes writable again, only the timers run
+    struct StalledRuntime;
+
+    impl AsyncRuntime for StalledRuntime {
+        type Registration = ();
+
+        unsafe fn register(&self, _fd: RawFd) -> io::Result<()> {
+            Ok(())
+        }
+
+        async fn writable(_registration: &()) -> io::Result<()> {
+            std::future::pending().await
+        }
+
+        async fn sleep(duration: Duration) {
+            tokio::time::sleep(duration).await
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_press_tracks_only_committed_keys() {
+        let (mut reader, device) = pipe_device();
+        let mut device = AsyncVirtualDevice::new(device, &TimedOutRuntime).unwrap();
+
+        let err = device.write_batch(&presses(20)).await.unwrap_err();
+        let err = err.downcast_ref::<WriteError>().unwrap();
+        assert!(matches!(err, WriteError::Io { committed: 170, .. }), "{err:?}");
+
+        let expected: Vec<Key> = (1..=10).map(Key).collect();
+        assert_eq!(device.device().pressed_keys(), expected);
+        drain(&mut reader);
+
+        device.device_mut().release_all().unwrap();
+        let mut releases: Vec<EventParams> = (1..=10).rev().map(|code| (EV_KEY, code, 0)).collect();
+        releases.push(SYN_PARAMS);
+        assert_eq!(read_events(&mut reader), releases);
+    }
+
+    #[tokio::test]
+    async fn writes_whole_press_batch() {
+        let (mut reader, device) = pipe_device();
+        let mut device = AsyncVirtualDevice::new(device, &TimedOutRuntime).unwrap();
+
+        device.write_batch(&presses(5)).await.unwrap();
+        assert_eq!(device.device().pressed_keys().len(), 5);
+        assert_eq!(read_events(&mut reader), presses(5));
+    }
+
+    // the releases go out through `write_batch`, nothing is left for the blocking drop
+    #[tokio::test]
+    async fn close_releases_held_keys_through_the_runtime() {
+        let (mut reader, device) = pipe_device();
+        let mut device = AsyncVirtualDevice::new(device, &TimedOutRuntime).unwrap();
+        device.write_batch(&presses(3)).await.unwrap();
+        drain(&mut reader);
+
+        device.close().await.unwrap();
+        assert_eq!(read_events(&mut reader), [(EV_KEY, 3, 0), (EV_KEY, 2, 0), (EV_KEY, 1, 0), SYN_PARAMS]);
+    }
+
+    #[tokio::test]
+    async fn moves_and_scrolls_leave_out_axes_that_dont_move() {
+        let (mut reader, device) = handle_device();
+        let mut device = AsyncVirtualDevice::new(device, &TimedOutRuntime).unwrap();
+
+        device.move_mouse(0, 0).await.unwrap();
+        device.scroll(0, 0).await.unwrap();
+        assert!(read_events(&mut reader).is_empty());
+
+        device.move_mouse(4, 0).await.unwrap();
+        device.scroll(0, -2).await.unwrap();
+        assert_eq!(read_events(&mut reader), [(EV_REL, REL_X, 4), SYN_PARAMS, (EV_REL, REL_WHEEL_HI_RES, -2), SYN_PARAMS]);
+    }
+
+    #[tokio::test]
+    async fn stalled_fd_times_out_after_the_write_timeout() {
+        let (_reader, mut device) = pipe_device();
+        device.set_write_timeout(Duration::from_millis(20));
+        let mut filler = device.file().try_clone().unwrap();
+        io::Write::write_all(&mut filler, &[0; 4096]).unwrap();
+        let mut device = AsyncVirtualDevice::new(device, &StalledRuntime).unwrap();
+
+        let started = Instant::now();
+        let err = device.write_batch(&[(EV_KEY, KEY_A, 1), SYN_PARAMS]).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<WriteError>(), Some(WriteError::Timeout { committed: 0 })), "{err:?}");
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        assert!(device.device().pressed_keys().is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancelled_write_is_finished_by_the_next_one() {
+        let (mut reader, device) = pipe_device();
+        let mut device = AsyncVirtualDevice::new(device, &StalledRuntime).unwrap();
+
+        // stops in the middle of the eleventh press and is dropped there
+        {
+            let batch = presses(20);
+            let mut write = pin!(device.write_batch(&batch));
+            let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+            assert!(write.as_mut().poll(&mut cx).is_pending());
+        }
+        let expected: Vec<Key> = (1..=10).map(Key).collect();
+        assert_eq!(device.device().pressed_keys(), expected);
+
+        let mut bytes = drain(&mut reader);
+        device.write_batch(&[SYN_PARAMS]).await.unwrap();
+        bytes.extend(drain(&mut reader));
+
+        assert_eq!(bytes.len() % EVENT_SIZE, 0, "a torn event reached the reader");
+        assert_eq!(bytes.len() / EVENT_SIZE, presses(20).len() + 1);
+        assert_eq!(device.device().pressed_keys().len(), 20);
+    }
+}
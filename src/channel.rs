@@ -0,0 +1,519 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, unbounded};
+
+use crate::{ChannelCommand, ChannelEvent, EmptyResult, Frame, Timing};
+
+const DISCONNECTED_ERR: &str = "Virtual device is disconnected, its flush thread has stopped";
+
+// what a sender does when `channel_size` events are already waiting for the flush thread
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backpressure {
+    #[default]
+    Block,
+    // fails with `ChannelFull` once the timeout passes, key transitions and scheduled frames included.
+    // a failed release leaves the key held until it is sent again or the device is dropped
+    BlockFor(Duration),
+    // the event that doesn't fit is discarded. key transitions and scheduled frames aren't,
+    // they wait for space like `Block`
+    DropNewest,
+    // the oldest motion-only frame queued by this handle and its clones is discarded instead,
+    // key transitions, scheduled frames and frames of other handles never are.
+    // blocks like `Block` when there is no motion to drop and the new event isn't motion either
+    DropOldestMotion,
+    // ignores `channel_size`
+    Unbounded,
+}
+
+#[derive(Debug, Default)]
+struct QueuedMotion {
+    queued: usize,
+    to_drop: usize,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    len: usize,
+    // immediate motion frames per sender, `DropOldestMotion` only drops those of its own sender
+    motion: HashMap<u64, QueuedMotion>,
+    // motion frames of all senders the receiver skips on behalf of `DropOldestMotion`
+    motion_to_drop: usize,
+    closed: bool,
+}
+
+#[derive(Debug)]
+struct ChannelState {
+    capacity: usize,
+    queue: Mutex<Queue>,
+    space: Condvar,
+    sender_ids: AtomicU64,
+}
+
+impl ChannelState {
+    #[inline]
+    fn is_full(&self, queue: &Queue) -> bool {
+        // motion that is going to be dropped doesn't take up space
+        !queue.closed && queue.len - queue.motion_to_drop >= self.capacity
+    }
+}
+
+// events carry the time they were sent, the flush thread orders immediate frames by it
+pub(crate) type SentEvent = (Instant, ChannelEvent);
+// what goes through the channel, tagged with the id of the sender policy
+pub(crate) type TaggedEvent = (u64, SentEvent);
+
+// only immediate motion can be dropped, scheduled frames are part of a timed sequence
+#[inline]
+fn is_motion(event: &ChannelEvent) -> bool {
+    match event {
+        ChannelEvent::Now(frame) => !frame.is_empty() && frame.commands().iter().all(|command| match command {
+            ChannelCommand::Move(..) | ChannelCommand::Scroll(..) => true,
+            ChannelCommand::Event((kind, ..)) => *kind == crate::key_codes::EV_REL,
+        }),
+        _ => false,
+    }
+}
+
+// a lost press or release leaves the key state out of sync and a lost scheduled frame breaks its sequence,
+// so no policy drops these
+#[inline]
+fn is_protected(event: &ChannelEvent) -> bool {
+    match event {
+        ChannelEvent::Now(frame) => frame.commands().iter().any(|command| {
+            matches!(command, ChannelCommand::Event((kind, ..)) if *kind == crate::key_codes::EV_KEY)
+        }),
+        _ => true,
+    }
+}
+
+// returned by a `Backpressure::BlockFor` sender when the channel stayed full for the whole timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelFull {
+    timeout: Duration,
+}
+
+impl ChannelFull {
+    #[inline]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl fmt::Display for ChannelFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Channel stayed full for {:?}", self.timeout)
+    }
+}
+
+impl Error for ChannelFull {}
+
+pub(crate) fn channel(capacity: usize) -> (ChannelSender, ChannelReceiver) {
+    let (sender, receiver) = unbounded();
+    let state = Arc::new(ChannelState {
+        capacity: capacity.max(1),
+        queue: Mutex::new(Queue::default()),
+        space: Condvar::new(),
+        sender_ids: AtomicU64::new(1),
+    });
+
+    let sender = ChannelSender {
+        sender,
+        id: 0,
+        state: state.clone(),
+        backpressure: Backpressure::default(),
+        dropped: Arc::new(AtomicU64::new(0)),
+        timeline: Arc::new(Mutex::new(Instant::now())),
+        timing: Arc::new(Mutex::new(Timing::default())),
+    };
+    (sender, ChannelReceiver { receiver, state })
+}
+
+// clones share the backpressure policy, the drop counter and their queued motion,
+// `with_backpressure` starts a new policy with its own counter and motion.
+// the timeline and the timing belong to the device, so every sender shares them
+#[derive(Clone, Debug)]
+pub struct ChannelSender {
+    sender: Sender<TaggedEvent>,
+    id: u64,
+    state: Arc<ChannelState>,
+    backpressure: Backpressure,
+    dropped: Arc<AtomicU64>,
+    // when the last sequence sent through any sender of the device ends, nothing sent later is due before it
+    timeline: Arc<Mutex<Instant>>,
+    // the timing profile of the device for the static `VirtualDevice::send_*` clicks
+    timing: Arc<Mutex<Timing>>,
+}
+
+impl ChannelSender {
+    pub fn with_backpressure(&self, backpressure: Backpressure) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            id: self.state.sender_ids.fetch_add(1, Ordering::Relaxed),
+            state: self.state.clone(),
+            backpressure,
+            dropped: Arc::new(AtomicU64::new(0)),
+            timeline: self.timeline.clone(),
+            timing: self.timing.clone(),
+        }
+    }
+
+    #[inline]
+    pub fn backpressure(&self) -> Backpressure {
+        self.backpressure
+    }
+
+    #[inline]
+    pub fn channel_size(&self) -> usize {
+        self.state.capacity
+    }
+
+    #[inline]
+    pub(crate) fn timing(&self) -> MutexGuard<'_, Timing> {
+        self.timing.lock().unwrap()
+    }
+
+    // events discarded by the policy of this sender and its clones
+    #[inline]
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    // frames sent while a sequence is still being written are due once it ends,
+    // so they never land between its press and release
+    pub fn send(&self, event: ChannelEvent) -> EmptyResult {
+        let end = *self.timeline.lock().unwrap();
+        let event = match event {
+            ChannelEvent::Now(frame) if end > Instant::now() => ChannelEvent::At(end, frame),
+            ChannelEvent::At(deadline, frame) => ChannelEvent::At(deadline.max(end), frame),
+            ChannelEvent::Scheduled(id, deadline, frame) => ChannelEvent::Scheduled(id, deadline.max(end), frame),
+            event => event,
+        };
+        self.send_event(event)
+    }
+
+    // frames are (offset from the start, frame). the sequence starts now or, if an earlier sequence
+    // is still running, right after it, and everything sent afterwards waits for it to end
+    pub(crate) fn send_sequence(&self, frames: Vec<(Duration, Frame)>) -> EmptyResult {
+        let length = frames.iter().map(|(offset, _)| *offset).max().unwrap_or_default();
+        let start = {
+            let mut end = self.timeline.lock().unwrap();
+            let start = (*end).max(Instant::now());
+            *end = start + length;
+            start
+        };
+
+        for (offset, frame) in frames {
+            self.send_event(ChannelEvent::At(start + offset, frame))?;
+        }
+        Ok(())
+    }
+
+    fn send_event(&self, event: ChannelEvent) -> EmptyResult {
+        let motion = is_motion(&event);
+        let mut queue = self.state.queue.lock().unwrap();
+
+        if self.backpressure != Backpressure::Unbounded && self.state.is_full(&queue) {
+            queue = match self.backpressure {
+                Backpressure::DropNewest if is_protected(&event) => self.wait_for_space(queue),
+                Backpressure::BlockFor(timeout) => {
+                    let (queue, result) = self.state.space
+                        .wait_timeout_while(queue, timeout, |queue| self.state.is_full(queue))
+                        .unwrap();
+                    if result.timed_out() {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                        return Err(Box::new(ChannelFull { timeout }));
+                    }
+                    queue
+                }
+                Backpressure::DropNewest => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                Backpressure::DropOldestMotion if self.has_motion_to_drop(&queue) => {
+                    queue.motion.entry(self.id).or_default().to_drop += 1;
+                    queue.motion_to_drop += 1;
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    queue
+                }
+                Backpressure::DropOldestMotion if motion => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                _ => self.wait_for_space(queue),
+            };
+        }
+        if queue.closed {
+            return Err(Box::from(DISCONNECTED_ERR));
+        }
+
+        queue.len += 1;
+        if motion {
+            queue.motion.entry(self.id).or_default().queued += 1;
+        }
+        // sent under the lock so the receiver never sees an event that isn't counted yet,
+        // and so the timestamps grow in the order the events are received
+        self.sender.send((self.id, (Instant::now(), event))).map_err(|_| Box::from(DISCONNECTED_ERR))
+    }
+
+    #[inline]
+    fn has_motion_to_drop(&self, queue: &Queue) -> bool {
+        queue.motion.get(&self.id).is_some_and(|motion| motion.queued > motion.to_drop)
+    }
+
+    #[inline]
+    fn wait_for_space<'a>(&self, queue: MutexGuard<'a, Queue>) -> MutexGuard<'a, Queue> {
+        self.state.space.wait_while(queue, |queue| self.state.is_full(queue)).unwrap()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ChannelReceiver {
+    receiver: Receiver<TaggedEvent>,
+    state: Arc<ChannelState>,
+}
+
+impl ChannelReceiver {
+    // `None` for motion dropped by `Backpressure::DropOldestMotion`
+    pub(crate) fn accept(&self, (sender_id, event): TaggedEvent) -> Option<SentEvent> {
+        let mut queue = self.state.queue.lock().unwrap();
+        queue.len -= 1;
+
+        let mut keep = true;
+        if is_motion(&event.1) {
+            let motion = queue.motion.get_mut(&sender_id).expect("queued motion is counted for its sender");
+            motion.queued -= 1;
+            if motion.to_drop > 0 {
+                motion.to_drop -= 1;
+                keep = false;
+            }
+            if motion.queued == 0 {
+                queue.motion.remove(&sender_id);
+            }
+            if !keep {
+                queue.motion_to_drop -= 1;
+            }
+        }
+        drop(queue);
+
+        self.state.space.notify_all();
+        keep.then_some(event)
+    }
+
+    #[inline]
+    pub(crate) fn inner(&self) -> &Receiver<TaggedEvent> {
+        &self.receiver
+    }
+
+    pub(crate) fn try_iter(&self) -> impl Iterator<Item = SentEvent> + '_ {
+        self.receiver.try_iter().filter_map(|event| self.accept(event))
+    }
+
+    // waits until `deadline` for the next event that isn't dropped
+    pub(crate) fn recv_deadline(&self, deadline: Instant) -> Option<SentEvent> {
+        loop {
+            match self.receiver.recv_deadline(deadline) {
+                Ok(event) => {
+                    if let Some(event) = self.accept(event) {
+                        return Some(event);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for ChannelReceiver {
+    fn drop(&mut self) {
+        // wakes up blocked senders so they see the disconnection
+        self.state.queue.lock().unwrap().closed = true;
+        self.state.space.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Frame, Key};
+
+    fn motion(x: f64) -> ChannelEvent {
+        ChannelEvent::Now(ChannelCommand::Move(x, 0.0).into())
+    }
+
+    fn press() -> ChannelEvent {
+        ChannelEvent::Now(Frame::builder().press(Key::A).build())
+    }
+
+    fn received(receiver: &ChannelReceiver) -> Vec<ChannelEvent> {
+        receiver.try_iter().map(|(_, event)| event).collect()
+    }
+
+    #[test]
+    fn drop_newest_discards_what_doesnt_fit() {
+        let (sender, receiver) = channel(2);
+        let sender = sender.with_backpressure(Backpressure::DropNewest);
+
+        for x in [1.0, 2.0, 3.0, 4.0] {
+            sender.send(motion(x)).unwrap();
+        }
+
+        assert_eq!(sender.dropped(), 2);
+        assert_eq!(received(&receiver), [motion(1.0), motion(2.0)]);
+    }
+
+    #[test]
+    fn drop_oldest_motion_keeps_key_transitions() {
+        let (sender, receiver) = channel(2);
+        let sender = sender.with_backpressure(Backpressure::DropOldestMotion);
+
+        sender.send(motion(1.0)).unwrap();
+        sender.send(press()).unwrap();
+        // each new motion pushes out the oldest queued one
+        sender.send(motion(2.0)).unwrap();
+        sender.send(motion(3.0)).unwrap();
+
+        assert_eq!(sender.dropped(), 2);
+        assert_eq!(received(&receiver), [press(), motion(3.0)]);
+    }
+
+    #[test]
+    fn drop_oldest_motion_discards_new_motion_when_none_is_queued() {
+        let (sender, receiver) = channel(1);
+        let sender = sender.with_backpressure(Backpressure::DropOldestMotion);
+
+        sender.send(press()).unwrap();
+        sender.send(motion(1.0)).unwrap();
+
+        assert_eq!(sender.dropped(), 1);
+        assert_eq!(received(&receiver), [press()]);
+    }
+
+    #[test]
+    fn drop_oldest_motion_only_drops_motion_of_its_own_sender() {
+        let (blocking, receiver) = channel(2);
+        let dropping = blocking.with_backpressure(Backpressure::DropOldestMotion);
+
+        // the queue is full of motion of the blocking sender, so the new motion goes instead
+        blocking.send(motion(1.0)).unwrap();
+        blocking.send(motion(2.0)).unwrap();
+        dropping.send(motion(3.0)).unwrap();
+        assert_eq!(received(&receiver), [motion(1.0), motion(2.0)]);
+
+        // clones push out each other's motion
+        dropping.clone().send(motion(4.0)).unwrap();
+        blocking.send(motion(5.0)).unwrap();
+        dropping.send(motion(6.0)).unwrap();
+        assert_eq!(received(&receiver), [motion(5.0), motion(6.0)]);
+
+        assert_eq!((dropping.dropped(), blocking.dropped()), (2, 0));
+    }
+
+    #[test]
+    fn scheduled_frames_are_never_dropped() {
+        let (sender, receiver) = channel(1);
+        let sender = sender.with_backpressure(Backpressure::DropOldestMotion);
+        let at = Instant::now();
+        let scheduled = ChannelEvent::At(at, ChannelCommand::Move(1.0, 0.0).into());
+
+        sender.send(scheduled.clone()).unwrap();
+        sender.send(motion(2.0)).unwrap();
+
+        assert_eq!(sender.dropped(), 1);
+        assert_eq!(received(&receiver), [scheduled]);
+    }
+
+    fn release() -> ChannelEvent {
+        ChannelEvent::Now(Frame::builder().release(Key::A).build())
+    }
+
+    #[test]
+    fn drop_newest_waits_for_key_transitions_and_scheduled_frames() {
+        let (sender, receiver) = channel(1);
+        let sender = sender.with_backpressure(Backpressure::DropNewest);
+        let scheduled = ChannelEvent::At(Instant::now(), ChannelCommand::Move(1.0, 0.0).into());
+        sender.send(motion(1.0)).unwrap();
+
+        let blocked = {
+            let sender = sender.clone();
+            let scheduled = scheduled.clone();
+            std::thread::spawn(move || {
+                sender.send(press()).unwrap();
+                sender.send(release()).unwrap();
+                sender.send(scheduled).unwrap();
+            })
+        };
+        let mut events = Vec::new();
+        while events.len() < 4 {
+            events.extend(received(&receiver));
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        blocked.join().unwrap();
+
+        assert_eq!(sender.dropped(), 0);
+        assert_eq!(events, [motion(1.0), press(), release(), scheduled]);
+    }
+
+    #[test]
+    fn block_for_gives_up_after_the_timeout() {
+        let timeout = Duration::from_millis(10);
+        let (sender, receiver) = channel(1);
+        let sender = sender.with_backpressure(Backpressure::BlockFor(timeout));
+        let scheduled = ChannelEvent::At(Instant::now(), ChannelCommand::Move(1.0, 0.0).into());
+
+        sender.send(press()).unwrap();
+        // releases and scheduled frames don't wait longer than anything else
+        for event in [press(), release(), scheduled] {
+            let err = sender.send(event).unwrap_err();
+            assert_eq!(err.downcast_ref::<ChannelFull>(), Some(&ChannelFull { timeout }));
+        }
+        assert_eq!(sender.dropped(), 3);
+
+        // reading frees the space again
+        assert_eq!(received(&receiver).len(), 1);
+        sender.send(release()).unwrap();
+    }
+
+    #[test]
+    fn unbounded_ignores_the_channel_size() {
+        let (sender, receiver) = channel(1);
+        let sender = sender.with_backpressure(Backpressure::Unbounded);
+
+        for _ in 0..10 {
+            sender.send(press()).unwrap();
+        }
+        assert_eq!(sender.dropped(), 0);
+        assert_eq!(received(&receiver).len(), 10);
+    }
+
+    #[test]
+    fn clones_share_the_counter_and_new_policies_start_their_own() {
+        let (sender, _receiver) = channel(1);
+        let dropping = sender.with_backpressure(Backpressure::DropNewest);
+        let clone = dropping.clone();
+
+        dropping.send(motion(1.0)).unwrap();
+        clone.send(motion(1.0)).unwrap();
+        assert_eq!(dropping.dropped(), 1);
+
+        let other = dropping.with_backpressure(Backpressure::DropNewest);
+        other.send(motion(1.0)).unwrap();
+        assert_eq!((dropping.dropped(), other.dropped(), sender.dropped()), (1, 1, 0));
+    }
+
+    #[test]
+    fn dropping_the_receiver_disconnects_blocked_senders() {
+        let (sender, receiver) = channel(1);
+        sender.send(press()).unwrap();
+
+        let blocked = std::thread::spawn(move || sender.send(press()).is_err());
+        std::thread::sleep(Duration::from_millis(10));
+        drop(receiver);
+
+        assert!(blocked.join().unwrap());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::key_codes::*;
+use crate::{EventParams, SYN_PARAMS};
+
+// EV_REL deltas summed per axis, axes keep the order they first appeared in.
+// there are only REL_CNT axes, so the sums live on the stack
+struct RelSums {
+    sums: [(u16, i32); REL_CNT as usize],
+    len: usize,
+}
+
+impl Default for RelSums {
+    fn default() -> Self {
+        Self { sums: [(0, 0); REL_CNT as usize], len: 0 }
+    }
+}
+
+impl RelSums {
+    #[inline]
+    fn add(&mut self, code: u16, value: i32) {
+        match self.sums[..self.len].iter_mut().find(|(axis, _)| *axis == code) {
+            Some((_, sum)) => *sum = sum.saturating_add(value),
+            None => {
+                self.sums[self.len] = (code, value);
+                self.len += 1;
+            }
+        }
+    }
+
+    // returns false if nothing but zero sums were pending
+    fn drain_into(&mut self, out: &mut Vec<EventParams>) -> bool {
+        let len = out.len();
+        out.extend(self.sums[..self.len].iter().filter(|(_, sum)| *sum != 0).map(|&(code, sum)| (EV_REL, code, sum)));
+        self.len = 0;
+        out.len() > len
+    }
+}
+
+// codes past REL_MAX aren't axes, they're passed through like key events
+#[inline]
+fn is_rel_axis(event: &EventParams) -> bool {
+    event.0 == EV_REL && event.1 < REL_CNT
+}
+
+#[inline]
+fn is_syn_report(event: &EventParams) -> bool {
+    event.0 == EV_SYN && event.1 == SYN_REPORT
+}
+
+// frames that only carry EV_REL are merged into one, deltas of the same axis are summed.
+// any other event is a barrier: motion sent before it is written before it and motion sent after it stays after it.
+// the result is appended to `out`
+pub(crate) fn coalesce(batch: &[EventParams], out: &mut Vec<EventParams>) {
+    let mut motion = RelSums::default();
+
+    for frame in batch.split_inclusive(is_syn_report) {
+        let events = match frame.split_last() {
+            Some((last, events)) if is_syn_report(last) => events,
+            _ => frame,
+        };
+
+        if events.iter().all(is_rel_axis) {
+            for &(_, code, value) in events {
+                motion.add(code, value);
+            }
+            continue;
+        }
+
+        if motion.drain_into(out) {
+            out.push(SYN_PARAMS);
+        }
+        for &event in events {
+            if is_rel_axis(&event) {
+                motion.add(event.1, event.2);
+            } else {
+                motion.drain_into(out);
+                out.push(event);
+            }
+        }
+        motion.drain_into(out);
+        out.push(SYN_PARAMS);
+    }
+
+    if motion.drain_into(out) {
+        out.push(SYN_PARAMS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coalesced(batch: &[EventParams]) -> Vec<EventParams> {
+        let mut out = Vec::new();
+        coalesce(batch, &mut out);
+        out
+    }
+
+    #[test]
+    fn sums_motion_frames_per_axis() {
+        let batch = [
+            (EV_REL, REL_X, 1), (EV_REL, REL_Y, 2), SYN_PARAMS,
+            (EV_REL, REL_X, 3), SYN_PARAMS,
+            (EV_REL, REL_WHEEL_HI_RES, 1), (EV_REL, REL_Y, -1), SYN_PARAMS,
+        ];
+        assert_eq!(coalesced(&batch), [(EV_REL, REL_X, 4), (EV_REL, REL_Y, 1), (EV_REL, REL_WHEEL_HI_RES, 1), SYN_PARAMS]);
+    }
+
+    #[test]
+    fn keys_are_barriers() {
+        let batch = [
+            (EV_REL, REL_X, 1), SYN_PARAMS,
+            (EV_KEY, BTN_LEFT, 1), SYN_PARAMS,
+            (EV_REL, REL_X, 2), SYN_PARAMS,
+            (EV_REL, REL_X, 3), SYN_PARAMS,
+            (EV_KEY, BTN_LEFT, 0), SYN_PARAMS,
+        ];
+        assert_eq!(coalesced(&batch), [
+            (EV_REL, REL_X, 1), SYN_PARAMS,
+            (EV_KEY, BTN_LEFT, 1), SYN_PARAMS,
+            (EV_REL, REL_X, 5), SYN_PARAMS,
+            (EV_KEY, BTN_LEFT, 0), SYN_PARAMS,
+        ]);
+    }
+
+    #[test]
+    fn motion_inside_a_key_frame_keeps_its_place() {
+        let batch = [(EV_REL, REL_X, 1), (EV_KEY, KEY_A, 1), (EV_REL, REL_X, 2), SYN_PARAMS];
+        assert_eq!(coalesced(&batch), [(EV_REL, REL_X, 1), (EV_KEY, KEY_A, 1), (EV_REL, REL_X, 2), SYN_PARAMS]);
+    }
+
+    #[test]
+    fn drops_zero_sums_and_empty_frames() {
+        let batch = [
+            (EV_REL, REL_X, 5), SYN_PARAMS,
+            SYN_PARAMS,
+            (EV_REL, REL_X, -5), (EV_REL, REL_Y, 1), SYN_PARAMS,
+        ];
+        assert_eq!(coalesced(&batch), [(EV_REL, REL_Y, 1), SYN_PARAMS]);
+
+        assert!(coalesced(&[SYN_PARAMS, SYN_PARAMS]).is_empty());
+        assert!(coalesced(&[(EV_REL, REL_X, 1), SYN_PARAMS, (EV_REL, REL_X, -1), SYN_PARAMS]).is_empty());
+    }
+
+    #[test]
+    fn codes_past_rel_max_are_not_summed() {
+        let batch = [(EV_REL, REL_CNT, 1), SYN_PARAMS, (EV_REL, REL_CNT, 1), SYN_PARAMS];
+        assert_eq!(coalesced(&batch), batch);
+    }
+}
@@ -0,0 +1,8 @@
+// typed names for the constants of key_codes.rs: `KEY_A` is `Key::A`, `REL_X` is `RelAxis::X`.
+// buttons keep their prefix (`Key::BTN_LEFT`) and so do keys whose name starts with a digit (`Key::KEY_1`).
+// build.rs generates them from key_codes.rs, so a new constant gets its typed name too
+use crate::codes::code_type;
+use crate::key_codes::*;
+use crate::{AbsAxis, Key, RelAxis};
+
+include!(concat!(env!("OUT_DIR"), "/code_names.rs"));
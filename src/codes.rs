@@ -0,0 +1,340 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+
+use crate::key_codes::*;
+use crate::virtual_device::SCROLL_AXES;
+use crate::{ChannelCommand, Coord, EmptyResult, EventParams, Frame};
+
+macro_rules! code_type {
+    ($name:ident) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(pub u16);
+
+        impl $name {
+            #[inline]
+            pub const fn code(self) -> u16 {
+                self.0
+            }
+        }
+
+        impl From<u16> for $name {
+            #[inline]
+            fn from(code: u16) -> Self {
+                Self(code)
+            }
+        }
+
+        impl From<$name> for u16 {
+            #[inline]
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+    };
+    // typed names for raw constants, generated by build.rs
+    (impl $name:ident { $($alias:ident = $code:ident,)* }) => {
+        impl $name {
+            $(pub const $alias: Self = Self($code);)*
+        }
+    };
+}
+pub(crate) use code_type;
+
+code_type!(EventType);
+code_type!(Key);
+code_type!(RelAxis);
+code_type!(AbsAxis);
+
+impl EventType {
+    pub const SYN: Self = Self(EV_SYN);
+    pub const KEY: Self = Self(EV_KEY);
+    pub const REL: Self = Self(EV_REL);
+    pub const ABS: Self = Self(EV_ABS);
+    pub const MSC: Self = Self(EV_MSC);
+    pub const SW: Self = Self(EV_SW);
+    pub const LED: Self = Self(EV_LED);
+    pub const SND: Self = Self(EV_SND);
+    pub const REP: Self = Self(EV_REP);
+    pub const FF: Self = Self(EV_FF);
+    pub const PWR: Self = Self(EV_PWR);
+    pub const FF_STATUS: Self = Self(EV_FF_STATUS);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyState {
+    Released,
+    Pressed,
+    Repeated,
+}
+
+impl From<KeyState> for i32 {
+    fn from(state: KeyState) -> Self {
+        match state {
+            KeyState::Released => 0,
+            KeyState::Pressed => 1,
+            KeyState::Repeated => 2,
+        }
+    }
+}
+
+impl TryFrom<i32> for KeyState {
+    type Error = UnknownKeyState;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(KeyState::Released),
+            1 => Ok(KeyState::Pressed),
+            2 => Ok(KeyState::Repeated),
+            _ => Err(UnknownKeyState(value)),
+        }
+    }
+}
+
+// the value of a key event is none of release, press and repeat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownKeyState(pub i32);
+
+impl fmt::Display for UnknownKeyState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Key event value {} is not 0 (released), 1 (pressed) or 2 (repeated)", self.0)
+    }
+}
+
+impl Error for UnknownKeyState {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    Sync,
+    Key(Key, KeyState),
+    Rel(RelAxis, Coord),
+    Abs(AbsAxis, Coord),
+    Other(EventType, u16, i32),
+}
+
+impl InputEvent {
+    #[inline]
+    pub fn press(key: impl Into<Key>) -> Self {
+        InputEvent::Key(key.into(), KeyState::Pressed)
+    }
+
+    #[inline]
+    pub fn release(key: impl Into<Key>) -> Self {
+        InputEvent::Key(key.into(), KeyState::Released)
+    }
+
+    #[inline]
+    pub fn event_type(&self) -> EventType {
+        match self {
+            InputEvent::Sync => EventType::SYN,
+            InputEvent::Key(..) => EventType::KEY,
+            InputEvent::Rel(..) => EventType::REL,
+            InputEvent::Abs(..) => EventType::ABS,
+            InputEvent::Other(kind, ..) => *kind,
+        }
+    }
+}
+
+impl From<InputEvent> for EventParams {
+    fn from(event: InputEvent) -> Self {
+        match event {
+            InputEvent::Sync => (EV_SYN, SYN_REPORT, 0),
+            InputEvent::Key(key, state) => (EV_KEY, key.0, state.into()),
+            InputEvent::Rel(axis, value) => (EV_REL, axis.0, value),
+            InputEvent::Abs(axis, value) => (EV_ABS, axis.0, value),
+            InputEvent::Other(kind, code, value) => (kind.0, code, value),
+        }
+    }
+}
+
+impl From<EventParams> for InputEvent {
+    fn from((kind, code, value): EventParams) -> Self {
+        match kind {
+            EV_SYN if code == SYN_REPORT => InputEvent::Sync,
+            // a key event with an unknown value stays raw and no device supports it
+            EV_KEY => match KeyState::try_from(value) {
+                Ok(state) => InputEvent::Key(Key(code), state),
+                Err(_) => InputEvent::Other(EventType(kind), code, value),
+            },
+            EV_REL => InputEvent::Rel(RelAxis(code), value),
+            EV_ABS => InputEvent::Abs(AbsAxis(code), value),
+            _ => InputEvent::Other(EventType(kind), code, value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    keys: HashSet<Key>,
+    rel_axes: HashSet<RelAxis>,
+    abs_axes: HashSet<AbsAxis>,
+}
+
+impl Capabilities {
+    #[inline]
+    pub fn has_key(&self, key: Key) -> bool {
+        self.keys.contains(&key)
+    }
+
+    #[inline]
+    pub fn has_rel(&self, axis: RelAxis) -> bool {
+        self.rel_axes.contains(&axis)
+    }
+
+    #[inline]
+    pub fn has_abs(&self, axis: AbsAxis) -> bool {
+        self.abs_axes.contains(&axis)
+    }
+
+    // devices of this crate only register keys and relative and absolute axes,
+    // so other events are never supported and `emit` rejects them
+    pub fn supports(&self, event: &InputEvent) -> bool {
+        match *event {
+            InputEvent::Sync => true,
+            InputEvent::Key(key, _) => self.has_key(key),
+            InputEvent::Rel(axis, _) => self.has_rel(axis),
+            InputEvent::Abs(axis, _) => self.has_abs(axis),
+            InputEvent::Other(..) => false,
+        }
+    }
+
+    pub(crate) fn ensure_key(&self, key: Key) -> EmptyResult {
+        if !self.has_key(key) {
+            return Err(Box::from(format!(
+                "Key code {} is not registered on this virtual device", key.code()
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn ensure_rel(&self, axis: RelAxis) -> EmptyResult {
+        if !self.has_rel(axis) {
+            return Err(Box::from(format!(
+                "Relative axis {} is not registered on this virtual device", axis.code()
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn ensure_abs(&self, axis: AbsAxis) -> EmptyResult {
+        if !self.has_abs(axis) {
+            return Err(Box::from(format!(
+                "Absolute axis {} is not registered on this virtual device", axis.code()
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn ensure_supported(&self, event: &InputEvent) -> EmptyResult {
+        match *event {
+            InputEvent::Key(key, _) => self.ensure_key(key),
+            InputEvent::Rel(axis, _) => self.ensure_rel(axis),
+            InputEvent::Abs(axis, _) => self.ensure_abs(axis),
+            _ if self.supports(event) => Ok(()),
+            _ => Err(Box::from(format!(
+                "Event {:?} is not supported by this virtual device", event
+            ))),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn ensure_motion(&self) -> EmptyResult {
+        self.ensure_rel(RelAxis::X)?;
+        self.ensure_rel(RelAxis::Y)
+    }
+
+    #[inline]
+    pub(crate) fn ensure_scroll(&self) -> EmptyResult {
+        for axis in SCROLL_AXES {
+            self.ensure_rel(axis)?;
+        }
+        Ok(())
+    }
+
+    // what the flush thread would write for the frame, checked before it is sent
+    pub(crate) fn ensure_frame(&self, frame: &Frame) -> EmptyResult {
+        for command in frame.commands() {
+            match *command {
+                ChannelCommand::Event(event) => self.ensure_supported(&event.into())?,
+                ChannelCommand::Move(..) => self.ensure_motion()?,
+                ChannelCommand::Scroll(..) => self.ensure_scroll()?,
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn add_key(&mut self, key: Key) {
+        self.keys.insert(key);
+    }
+
+    pub(crate) fn add_rel(&mut self, axis: RelAxis) {
+        self.rel_axes.insert(axis);
+    }
+
+    pub(crate) fn add_abs(&mut self, axis: AbsAxis) {
+        self.abs_axes.insert(axis);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_codes_convert_to_and_from_raw_codes() {
+        assert_eq!(u16::from(Key(KEY_A)), KEY_A);
+        assert_eq!(RelAxis::from(REL_WHEEL_HI_RES), RelAxis(REL_WHEEL_HI_RES));
+        assert_eq!(AbsAxis(ABS_Y).code(), ABS_Y);
+        assert_eq!(RelAxis::WHEEL_NOTCH.code(), 0x08);
+        assert_eq!(Key::KEY_1, Key(KEY_1));
+        assert_eq!(EventType::from(EV_KEY), EventType::KEY);
+    }
+
+    #[test]
+    fn input_events_round_trip_through_event_params() {
+        let events = [
+            InputEvent::Sync,
+            InputEvent::press(KEY_A),
+            InputEvent::release(Key(KEY_B)),
+            InputEvent::Key(Key(KEY_C), KeyState::Repeated),
+            InputEvent::Rel(RelAxis(REL_X), -5),
+            InputEvent::Abs(AbsAxis(ABS_X), 300),
+            InputEvent::Other(EventType::MSC, 4, 7),
+            InputEvent::Other(EventType::SW, SW_LID, 1),
+        ];
+        for event in events {
+            let params = EventParams::from(event);
+            assert_eq!(params.0, event.event_type().code());
+            assert_eq!(InputEvent::from(params), event);
+        }
+        assert_eq!(EventParams::from(InputEvent::press(KEY_A)), (EV_KEY, KEY_A, 1));
+        // only SYN_REPORT is a plain sync
+        assert_eq!(InputEvent::from((EV_SYN, SYN_DROPPED, 0)), InputEvent::Other(EventType::SYN, SYN_DROPPED, 0));
+        // and only the three key states make a key event
+        assert_eq!(InputEvent::from((EV_KEY, KEY_A, 7)), InputEvent::Other(EventType::KEY, KEY_A, 7));
+    }
+
+    #[test]
+    fn unknown_key_states_are_rejected() {
+        assert_eq!(KeyState::try_from(1), Ok(KeyState::Pressed));
+        assert_eq!(KeyState::try_from(2), Ok(KeyState::Repeated));
+        assert_eq!(KeyState::try_from(-1), Err(UnknownKeyState(-1)));
+        assert_eq!(KeyState::try_from(7), Err(UnknownKeyState(7)));
+    }
+
+    #[test]
+    fn capabilities_support_only_what_was_registered() {
+        let mut capabilities = Capabilities::default();
+        capabilities.add_key(Key(KEY_A));
+        capabilities.add_rel(RelAxis(REL_X));
+
+        assert!(capabilities.supports(&InputEvent::Sync));
+        assert!(capabilities.supports(&InputEvent::press(KEY_A)));
+        assert!(!capabilities.supports(&InputEvent::press(KEY_B)));
+        assert!(capabilities.supports(&InputEvent::Rel(RelAxis(REL_X), 1)));
+        assert!(!capabilities.supports(&InputEvent::Rel(RelAxis(REL_Y), 1)));
+        assert!(!capabilities.supports(&InputEvent::Abs(AbsAxis(ABS_X), 1)));
+        assert!(!capabilities.supports(&InputEvent::from((EV_KEY, KEY_A, 7))));
+        assert!(!capabilities.supports(&InputEvent::Other(EventType::SW, SW_LID, 1)));
+    }
+}
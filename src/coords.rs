@@ -0,0 +1,107 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+// maps caller coordinates to screen space (x to the right, y down),
+// applied in order: scale, swap, Y flip, rotation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CoordinateSystem {
+    pub y_up: bool,
+    pub swap_axes: bool,
+    pub rotation: Rotation,
+    pub scale_x: f64,
+    pub scale_y: f64,
+}
+
+impl CoordinateSystem {
+    // x grows to the right and y grows down, like REL_X and REL_Y
+    pub const KERNEL: Self = Self {
+        y_up: false,
+        swap_axes: false,
+        rotation: Rotation::None,
+        scale_x: 1.0,
+        scale_y: 1.0,
+    };
+
+    // x grows to the right and y grows up
+    pub const Y_UP: Self = Self {
+        y_up: true,
+        ..Self::KERNEL
+    };
+
+    pub fn with_rotation(self, rotation: Rotation) -> Self {
+        Self { rotation, ..self }
+    }
+
+    pub fn with_scale(self, scale_x: f64, scale_y: f64) -> Self {
+        Self { scale_x, scale_y, ..self }
+    }
+
+    pub fn with_swapped_axes(self, swap_axes: bool) -> Self {
+        Self { swap_axes, ..self }
+    }
+
+    pub fn to_screen(&self, x: f64, y: f64) -> (f64, f64) {
+        let (mut x, mut y) = (x * self.scale_x, y * self.scale_y);
+
+        if self.swap_axes {
+            (x, y) = (y, x);
+        }
+        if self.y_up {
+            y = -y;
+        }
+
+        match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Clockwise90 => (-y, x),
+            Rotation::Clockwise180 => (-x, -y),
+            Rotation::Clockwise270 => (y, -x),
+        }
+    }
+}
+
+// the crate always treated positive Y as up
+impl Default for CoordinateSystem {
+    fn default() -> Self {
+        Self::Y_UP
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernel_is_the_identity() {
+        assert_eq!(CoordinateSystem::KERNEL.to_screen(3.0, -4.0), (3.0, -4.0));
+    }
+
+    #[test]
+    fn y_up_flips_y() {
+        assert_eq!(CoordinateSystem::Y_UP.to_screen(3.0, 4.0), (3.0, -4.0));
+        assert_eq!(CoordinateSystem::default(), CoordinateSystem::Y_UP);
+    }
+
+    #[test]
+    fn rotations_turn_clockwise() {
+        let right = |rotation| CoordinateSystem::KERNEL.with_rotation(rotation).to_screen(1.0, 0.0);
+        assert_eq!(right(Rotation::None), (1.0, 0.0));
+        assert_eq!(right(Rotation::Clockwise90), (0.0, 1.0));
+        assert_eq!(right(Rotation::Clockwise180), (-1.0, -0.0));
+        assert_eq!(right(Rotation::Clockwise270), (0.0, -1.0));
+    }
+
+    #[test]
+    fn scale_swap_flip_then_rotate() {
+        let system = CoordinateSystem::Y_UP
+            .with_scale(2.0, 3.0)
+            .with_swapped_axes(true)
+            .with_rotation(Rotation::Clockwise90);
+        // scaled (2, 3), swapped (3, 2), flipped (3, -2), rotated (2, 3)
+        assert_eq!(system.to_screen(1.0, 1.0), (2.0, 3.0));
+    }
+}
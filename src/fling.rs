@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use crate::Result;
+
+// below this speed (units per second) the fling is considered finished
+const MIN_FLING_VELOCITY: f64 = 10.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decay {
+    // v(t) = v0 * exp(-friction * t), a touchpad-like glide with a long tail
+    Exponential(f64),
+    // constant deceleration in units per second squared, stops at a predictable time
+    Linear(f64),
+}
+
+// kinetic scroll state, velocities are in wheel units per second
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fling {
+    velocity_x: f64,
+    velocity_y: f64,
+    decay: Decay,
+}
+
+impl Fling {
+    // a decay that isn't positive would never stop the fling
+    pub fn new(velocity_x: f64, velocity_y: f64, decay: Decay) -> Result<Self> {
+        if !velocity_x.is_finite() || !velocity_y.is_finite() {
+            return Err(Box::from(format!("Fling velocity must be finite, got ({velocity_x}, {velocity_y})")));
+        }
+        let rate = match decay {
+            Decay::Exponential(rate) | Decay::Linear(rate) => rate,
+        };
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(Box::from(format!("Fling decay must be positive and finite, got {decay:?}")));
+        }
+        Ok(Self { velocity_x, velocity_y, decay })
+    }
+
+    #[inline]
+    pub fn velocity(&self) -> (f64, f64) {
+        (self.velocity_x, self.velocity_y)
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.velocity_x.hypot(self.velocity_y) < MIN_FLING_VELOCITY
+    }
+
+    // advances the fling by `elapsed` and returns the distance travelled on each axis
+    pub fn advance(&mut self, elapsed: Duration) -> (f64, f64) {
+        if self.is_finished() {
+            return (0.0, 0.0);
+        }
+        let dt = elapsed.as_secs_f64();
+
+        match self.decay {
+            Decay::Exponential(friction) => {
+                let factor = (-friction * dt).exp();
+                let travelled = (1.0 - factor) / friction;
+
+                let distance = (self.velocity_x * travelled, self.velocity_y * travelled);
+                self.velocity_x *= factor;
+                self.velocity_y *= factor;
+                distance
+            }
+            Decay::Linear(deceleration) => {
+                let speed = self.velocity_x.hypot(self.velocity_y);
+                let dt = dt.min(speed / deceleration);
+
+                let travelled = speed * dt - deceleration * dt * dt / 2.0;
+                let new_speed = speed - deceleration * dt;
+                let (dir_x, dir_y) = (self.velocity_x / speed, self.velocity_y / speed);
+
+                self.velocity_x = dir_x * new_speed;
+                self.velocity_y = dir_y * new_speed;
+                (dir_x * travelled, dir_y * travelled)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-9 && (actual.1 - expected.1).abs() < 1e-9,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn exponential_decay_travels_the_integral_of_its_velocity() {
+        let mut fling = Fling::new(1000.0, -500.0, Decay::Exponential(2.0)).unwrap();
+
+        let travelled = (1.0 - (-1.0f64).exp()) / 2.0;
+        assert_close(fling.advance(Duration::from_millis(500)), (1000.0 * travelled, -500.0 * travelled));
+        assert_close(fling.velocity(), (1000.0 * (-1.0f64).exp(), -500.0 * (-1.0f64).exp()));
+
+        // small steps add up to the same distance as one long step
+        let mut stepped = Fling::new(1000.0, 0.0, Decay::Exponential(2.0)).unwrap();
+        let sum: f64 = (0..50).map(|_| stepped.advance(Duration::from_millis(10)).0).sum();
+        assert!((sum - 1000.0 * travelled).abs() < 1e-9, "{sum}");
+    }
+
+    #[test]
+    fn linear_decay_stops_after_velocity_over_deceleration() {
+        let mut fling = Fling::new(300.0, 400.0, Decay::Linear(1000.0)).unwrap();
+
+        assert_close(fling.advance(Duration::from_millis(250)), (75.0 * 0.75, 100.0 * 0.75));
+        assert!(!fling.is_finished());
+
+        // stopping takes half a second, the rest of this step moves nothing
+        assert_close(fling.advance(Duration::from_secs(1)), (75.0 * 0.25, 100.0 * 0.25));
+        assert_close(fling.velocity(), (0.0, 0.0));
+        assert!(fling.is_finished());
+        assert_eq!(fling.advance(Duration::from_secs(1)), (0.0, 0.0));
+    }
+
+    #[test]
+    fn decay_has_to_be_positive_and_finite() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(Fling::new(100.0, 0.0, Decay::Exponential(rate)).is_err(), "{rate}");
+            assert!(Fling::new(100.0, 0.0, Decay::Linear(rate)).is_err(), "{rate}");
+        }
+        assert!(Fling::new(f64::NAN, 0.0, Decay::Linear(1.0)).is_err());
+        assert!(Fling::new(0.0, 0.0, Decay::Linear(1.0)).unwrap().is_finished());
+    }
+}
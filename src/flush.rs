@@ -0,0 +1,89 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crossbeam_channel::Sender;
+use nix::errno::Errno;
+
+use crate::WriteError;
+
+// the flush thread stops at the first failed write, destroys the device and reports the error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlushError {
+    message: String,
+    raw_os_error: Option<i32>,
+}
+
+impl FlushError {
+    pub(crate) fn new(error: &(dyn Error + 'static)) -> Self {
+        let raw_os_error = if let Some(err) = error.downcast_ref::<WriteError>() {
+            err.raw_os_error()
+        } else if let Some(err) = error.downcast_ref::<io::Error>() {
+            err.raw_os_error()
+        } else {
+            error.downcast_ref::<Errno>().map(|errno| *errno as i32)
+        };
+        Self {
+            message: error.to_string(),
+            raw_os_error,
+        }
+    }
+
+    #[inline]
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.raw_os_error
+    }
+}
+
+impl fmt::Display for FlushError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Flush thread stopped: {}", self.message)
+    }
+}
+
+impl Error for FlushError {}
+
+// returned by `VirtualDevice::flush_channel_every_interval` and `flush_channel_blocking`,
+// dropping the handle leaves the thread running like dropping a `JoinHandle`
+#[must_use = "dropping the handle leaves the flush thread running, `stop` writes the rest and destroys the device"]
+#[derive(Debug)]
+pub struct FlushHandle {
+    stop: Sender<()>,
+    error: Arc<Mutex<Option<FlushError>>>,
+    thread: JoinHandle<()>,
+}
+
+impl FlushHandle {
+    pub(crate) fn new(stop: Sender<()>, error: Arc<Mutex<Option<FlushError>>>, thread: JoinHandle<()>) -> Self {
+        Self { stop, error, thread }
+    }
+
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        !self.thread.is_finished()
+    }
+
+    // the error that stopped the thread, if any
+    pub fn error(&self) -> Option<FlushError> {
+        self.error.lock().unwrap().clone()
+    }
+
+    // writes everything that was already sent, including scheduled events without waiting for them,
+    // then destroys the device. senders get a disconnected error afterwards
+    pub fn stop(self) -> Result<(), FlushError> {
+        // fails if the thread already stopped because of an error
+        let _ = self.stop.send(());
+        if self.thread.join().is_err() {
+            return Err(FlushError {
+                message: String::from("flush thread panicked"),
+                raw_os_error: None,
+            });
+        }
+        match self.error.lock().unwrap().take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use crate::key_codes::*;
+use crate::{ChannelCommand, EventParams, InputEvent, Key};
+
+// events written together and followed by exactly one SYN_REPORT,
+// frames from different threads never interleave
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frame {
+    commands: Vec<ChannelCommand>,
+}
+
+impl Frame {
+    #[inline]
+    pub fn builder() -> FrameBuilder {
+        FrameBuilder::new()
+    }
+
+    #[inline]
+    pub fn commands(&self) -> &[ChannelCommand] {
+        &self.commands
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    // any scroll cancels the running fling
+    pub(crate) fn has_scroll(&self) -> bool {
+        self.commands.iter().any(|command| match *command {
+            ChannelCommand::Event((kind, code, _)) => {
+                kind == EV_REL && matches!(code, REL_WHEEL_NOTCH | REL_HWHEEL_NOTCH | REL_WHEEL_HI_RES | REL_HWHEEL_HI_RES)
+            }
+            ChannelCommand::Scroll(..) => true,
+            ChannelCommand::Move(..) => false,
+        })
+    }
+}
+
+impl From<ChannelCommand> for Frame {
+    fn from(command: ChannelCommand) -> Self {
+        FrameBuilder::new().command(command).build()
+    }
+}
+
+impl From<EventParams> for Frame {
+    fn from(event: EventParams) -> Self {
+        ChannelCommand::Event(event).into()
+    }
+}
+
+impl From<InputEvent> for Frame {
+    fn from(event: InputEvent) -> Self {
+        FrameBuilder::new().event(event).build()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameBuilder {
+    frame: Frame,
+}
+
+impl FrameBuilder {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // SYN_REPORT is added when the frame is written, explicit ones are dropped
+    pub fn command(mut self, command: ChannelCommand) -> Self {
+        if !matches!(command, ChannelCommand::Event((EV_SYN, SYN_REPORT, _))) {
+            self.frame.commands.push(command);
+        }
+        self
+    }
+
+    #[inline]
+    pub fn event(self, event: InputEvent) -> Self {
+        self.command(ChannelCommand::Event(event.into()))
+    }
+
+    #[inline]
+    pub fn raw(self, kind: u16, code: u16, value: i32) -> Self {
+        self.command(ChannelCommand::Event((kind, code, value)))
+    }
+
+    #[inline]
+    pub fn press(self, key: impl Into<Key>) -> Self {
+        self.event(InputEvent::press(key))
+    }
+
+    #[inline]
+    pub fn release(self, key: impl Into<Key>) -> Self {
+        self.event(InputEvent::release(key))
+    }
+
+    // in the device coordinate system like `VirtualDevice::move_mouse_f`
+    #[inline]
+    pub fn move_by(self, x: f64, y: f64) -> Self {
+        self.command(ChannelCommand::Move(x, y))
+    }
+
+    #[inline]
+    pub fn scroll(self, x: f64, y: f64) -> Self {
+        self.command(ChannelCommand::Scroll(x, y))
+    }
+
+    #[inline]
+    pub fn build(self) -> Frame {
+        self.frame
+    }
+}
@@ -0,0 +1,303 @@
+use std::fs;
+use std::path::Path;
+
+use crate::{Coord, Result};
+
+// a monitor placed on the desktop, offset and size are in physical pixels,
+// `scale` converts the logical points used by applications into physical pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Monitor {
+    pub x: Coord,
+    pub y: Coord,
+    pub width: Coord,
+    pub height: Coord,
+    pub scale: f64,
+}
+
+impl Monitor {
+    pub fn new(x: Coord, y: Coord, width: Coord, height: Coord) -> Self {
+        Self { x, y, width, height, scale: 1.0 }
+    }
+
+    pub fn with_scale(self, scale: f64) -> Self {
+        Self { scale, ..self }
+    }
+
+    // size in logical points
+    pub fn logical_size(&self) -> (f64, f64) {
+        (self.width as f64 / self.scale, self.height as f64 / self.scale)
+    }
+
+    // logical point on this monitor to a physical desktop pixel
+    pub fn to_desktop(&self, x: f64, y: f64) -> (f64, f64) {
+        (self.x as f64 + x * self.scale, self.y as f64 + y * self.scale)
+    }
+
+    // (100, 100) is the last pixel of the monitor, not the first one of its neighbour
+    pub fn percent_to_desktop(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.x as f64 + x / 100.0 * (self.width - 1) as f64,
+            self.y as f64 + y / 100.0 * (self.height - 1) as f64,
+        )
+    }
+}
+
+// the desktop spanned by all monitors is stretched over the whole ABS range,
+// the same way a tablet device covers every screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScreenGeometry {
+    monitors: Vec<Monitor>,
+}
+
+impl ScreenGeometry {
+    pub fn new(monitors: Vec<Monitor>) -> Result<Self> {
+        if monitors.is_empty() {
+            return Err(Box::from("Screen geometry needs at least one monitor"));
+        }
+        for (idx, monitor) in monitors.iter().enumerate() {
+            if monitor.width <= 0 || monitor.height <= 0 || !monitor.scale.is_finite() || monitor.scale <= 0.0 {
+                return Err(Box::from(format!(
+                    "Monitor {idx} has invalid size {}x{} or scale {}",
+                    monitor.width, monitor.height, monitor.scale
+                )));
+            }
+            // `bounds` adds the size to the offset
+            if monitor.x.checked_add(monitor.width).is_none() || monitor.y.checked_add(monitor.height).is_none() {
+                return Err(Box::from(format!(
+                    "Monitor {idx} at {},{} with size {}x{} ends past the coordinate range",
+                    monitor.x, monitor.y, monitor.width, monitor.height
+                )));
+            }
+        }
+        Ok(Self { monitors })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    // one monitor per line: `x y width height [scale]`, empty lines and `#` comments are skipped
+    //
+    //     # laptop panel at 150% and an external screen to its right
+    //     0    0 2880 1800 1.5
+    //     2880 0 1920 1080
+    pub fn parse(config: &str) -> Result<Self> {
+        let mut monitors = Vec::new();
+
+        for (line_idx, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 4 && fields.len() != 5 {
+                return Err(Box::from(format!(
+                    "Line {}: expected `x y width height [scale]`, got '{line}'",
+                    line_idx + 1
+                )));
+            }
+
+            let parse_coord = |field: &str| {
+                field.parse::<Coord>().map_err(|err| format!("Line {}: '{field}' {err}", line_idx + 1))
+            };
+            let mut monitor = Monitor::new(
+                parse_coord(fields[0])?,
+                parse_coord(fields[1])?,
+                parse_coord(fields[2])?,
+                parse_coord(fields[3])?,
+            );
+            if let Some(scale) = fields.get(4) {
+                monitor.scale = scale.parse().map_err(|err| format!("Line {}: '{scale}' {err}", line_idx + 1))?;
+            }
+            monitors.push(monitor);
+        }
+
+        Self::new(monitors)
+    }
+
+    #[inline]
+    pub fn monitors(&self) -> &[Monitor] {
+        &self.monitors
+    }
+
+    pub fn monitor(&self, idx: usize) -> Result<&Monitor> {
+        self.monitors.get(idx).ok_or_else(|| {
+            Box::from(format!("Monitor {idx} doesn't exist, there are {} monitors", self.monitors.len()))
+        })
+    }
+
+    // (left, top, right, bottom) of the desktop in physical pixels, right and bottom are exclusive
+    pub fn bounds(&self) -> (Coord, Coord, Coord, Coord) {
+        self.monitors.iter().fold((Coord::MAX, Coord::MAX, Coord::MIN, Coord::MIN), |bounds, monitor| {
+            (
+                bounds.0.min(monitor.x),
+                bounds.1.min(monitor.y),
+                bounds.2.max(monitor.x + monitor.width),
+                bounds.3.max(monitor.y + monitor.height),
+            )
+        })
+    }
+
+    // physical desktop pixel to device units, the last pixel of the desktop maps to `max`
+    pub fn desktop_to_abs(&self, x: f64, y: f64, range_x: (Coord, Coord), range_y: (Coord, Coord)) -> (Coord, Coord) {
+        let (left, top, right, bottom) = self.bounds();
+        (
+            // the desktop can be wider than a coordinate, so the span is computed in f64
+            scale_to_range(x - left as f64, right as f64 - left as f64 - 1.0, range_x),
+            scale_to_range(y - top as f64, bottom as f64 - top as f64 - 1.0, range_y),
+        )
+    }
+}
+
+#[inline]
+fn scale_to_range(value: f64, span: f64, (min, max): (Coord, Coord)) -> Coord {
+    let fraction = if span > 0.0 { (value / span).clamp(0.0, 1.0) } else { 0.0 };
+    // `max - min` of a full i32 range doesn't fit a coordinate
+    (min as f64 + (fraction * (max as f64 - min as f64)).round()) as Coord
+}
+
+#[inline]
+pub(crate) fn percent_to_range(percent: f64, (min, max): (Coord, Coord)) -> Coord {
+    scale_to_range(percent, 100.0, (min, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_monitors_with_optional_scale() {
+        let geometry = ScreenGeometry::parse(
+            "# laptop panel and an external screen\n\
+             0    0 2880 1800 1.5\n\
+             \n\
+             2880 -200 1920 1080  # rotated later\n",
+        ).unwrap();
+
+        assert_eq!(geometry.monitors(), [
+            Monitor::new(0, 0, 2880, 1800).with_scale(1.5),
+            Monitor::new(2880, -200, 1920, 1080),
+        ]);
+        assert_eq!(geometry.bounds(), (0, -200, 4800, 1800));
+    }
+
+    #[test]
+    fn reports_the_line_of_a_bad_field() {
+        let err = ScreenGeometry::parse("0 0 1920 1080\n0 0 wide 1080").unwrap_err();
+        assert!(err.to_string().starts_with("Line 2: 'wide'"), "{err}");
+
+        let err = ScreenGeometry::parse("0 0 1920").unwrap_err();
+        assert!(err.to_string().starts_with("Line 1: expected"), "{err}");
+
+        assert!(ScreenGeometry::parse("0 0 1920 1080 fast").is_err());
+    }
+
+    #[test]
+    fn rejects_empty_and_invalid_monitors() {
+        assert!(ScreenGeometry::parse("# nothing here\n\n").is_err());
+        assert!(ScreenGeometry::parse("0 0 0 1080").is_err());
+        assert!(ScreenGeometry::parse("0 0 1920 1080 0").is_err());
+        assert!(ScreenGeometry::parse("0 0 1920 1080 NaN").is_err());
+        // the right edge doesn't fit a coordinate
+        assert!(ScreenGeometry::parse("2147483000 0 1920 1080").is_err());
+        assert!(ScreenGeometry::parse("0 2147483000 1920 1080").is_err());
+    }
+
+    #[test]
+    fn desktops_wider_than_a_coordinate_are_scaled() {
+        let geometry = ScreenGeometry::new(vec![
+            Monitor::new(Coord::MIN, 0, 1920, 1080),
+            Monitor::new(Coord::MAX - 1920, 0, 1920, 1080),
+        ]).unwrap();
+        let full = (Coord::MIN, Coord::MAX);
+
+        assert_eq!(geometry.desktop_to_abs(Coord::MIN as f64, 0.0, full, full), (Coord::MIN, Coord::MIN));
+        assert_eq!(geometry.desktop_to_abs(Coord::MAX as f64 - 1.0, 1079.0, full, full), (Coord::MAX, Coord::MAX));
+    }
+
+    // a scaled laptop panel, a monitor above and to the left of it and one lower on the right
+    fn mixed_layout() -> ScreenGeometry {
+        ScreenGeometry::new(vec![
+            Monitor::new(0, 0, 2880, 1800).with_scale(1.5),
+            Monitor::new(-1920, -200, 1920, 1080),
+            Monitor::new(2880, 300, 1920, 1080),
+        ]).unwrap()
+    }
+
+    // one device unit per desktop pixel, so ABS values are pixels shifted by the top left corner
+    const PIXEL_X: (Coord, Coord) = (0, 6719);
+    const PIXEL_Y: (Coord, Coord) = (0, 1999);
+
+    #[test]
+    fn desktop_corners_map_to_the_ends_of_the_range() {
+        let geometry = mixed_layout();
+        assert_eq!(geometry.bounds(), (-1920, -200, 4800, 1800));
+
+        let range = (0, 65535);
+        assert_eq!(geometry.desktop_to_abs(-1920.0, -200.0, range, range), (0, 0));
+        assert_eq!(geometry.desktop_to_abs(4799.0, 1799.0, range, range), (65535, 65535));
+        assert_eq!(geometry.desktop_to_abs(-1920.0, 1799.0, range, range), (0, 65535));
+
+        let signed = (-32768, 32767);
+        assert_eq!(geometry.desktop_to_abs(-1920.0, -200.0, signed, signed), (-32768, -32768));
+        assert_eq!(geometry.desktop_to_abs(4799.0, 1799.0, signed, signed), (32767, 32767));
+
+        // points outside of the desktop stay on its edge
+        assert_eq!(geometry.desktop_to_abs(5000.0, -500.0, range, range), (65535, 0));
+    }
+
+    #[test]
+    fn logical_points_are_scaled_per_monitor() {
+        let geometry = mixed_layout();
+        let to_abs = |idx: usize, x: f64, y: f64| {
+            let (x, y) = geometry.monitor(idx).unwrap().to_desktop(x, y);
+            geometry.desktop_to_abs(x, y, PIXEL_X, PIXEL_Y)
+        };
+
+        assert_eq!(geometry.monitors()[0].logical_size(), (1920.0, 1200.0));
+        assert_eq!(to_abs(0, 0.0, 0.0), (1920, 200));
+        assert_eq!(to_abs(0, 100.0, 10.0), (2070, 215));
+
+        assert_eq!(to_abs(1, 0.0, 0.0), (0, 0));
+        assert_eq!(to_abs(1, 1919.0, 1079.0), (1919, 1079));
+
+        assert_eq!(to_abs(2, 0.0, 0.0), (4800, 500));
+        assert_eq!(to_abs(2, 10.0, 20.0), (4810, 520));
+    }
+
+    #[test]
+    fn percentages_span_each_monitor_up_to_its_last_pixel() {
+        let geometry = mixed_layout();
+        let to_abs = |idx: usize, x: f64, y: f64| {
+            let (x, y) = geometry.monitor(idx).unwrap().percent_to_desktop(x, y);
+            geometry.desktop_to_abs(x, y, PIXEL_X, PIXEL_Y)
+        };
+
+        // the scale doesn't matter, percentages address physical pixels
+        assert_eq!(to_abs(0, 0.0, 0.0), (1920, 200));
+        assert_eq!(to_abs(0, 100.0, 100.0), (4799, 1999));
+
+        assert_eq!(to_abs(1, 0.0, 0.0), (0, 0));
+        assert_eq!(to_abs(1, 100.0, 100.0), (1919, 1079));
+
+        assert_eq!(to_abs(2, 0.0, 100.0), (4800, 1579));
+        assert_eq!(to_abs(2, 100.0, 0.0), (6719, 500));
+
+        assert!(geometry.monitor(3).is_err());
+    }
+
+    #[test]
+    fn percent_of_the_whole_range() {
+        assert_eq!(percent_to_range(0.0, (-100, 100)), -100);
+        assert_eq!(percent_to_range(50.0, (-100, 100)), 0);
+        assert_eq!(percent_to_range(100.0, (-100, 100)), 100);
+        assert_eq!(percent_to_range(50.0, (0, 65535)), 32768);
+
+        assert_eq!(percent_to_range(-10.0, (0, 65535)), 0);
+        assert_eq!(percent_to_range(150.0, (0, 65535)), 65535);
+
+        assert_eq!(percent_to_range(0.0, (Coord::MIN, Coord::MAX)), Coord::MIN);
+        assert_eq!(percent_to_range(100.0, (Coord::MIN, Coord::MAX)), Coord::MAX);
+    }
+}
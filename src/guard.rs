@@ -0,0 +1,139 @@
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::{Button, DeviceHandle, EmptyResult, Result, VirtualDevice};
+
+pub struct HoldGuard<'a> {
+    device: &'a mut VirtualDevice,
+    button: Button,
+}
+
+impl<'a> HoldGuard<'a> {
+    pub(crate) fn new(device: &'a mut VirtualDevice, button: Button) -> Self {
+        Self { device, button }
+    }
+
+    #[inline]
+    pub fn button(&self) -> Button {
+        self.button
+    }
+}
+
+impl Deref for HoldGuard<'_> {
+    type Target = VirtualDevice;
+
+    fn deref(&self) -> &Self::Target {
+        self.device
+    }
+}
+
+impl DerefMut for HoldGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.device
+    }
+}
+
+impl Drop for HoldGuard<'_> {
+    fn drop(&mut self) {
+        // the button may have been released manually through the guard
+        if self.device.is_pressed(self.button) {
+            let _ = self.device.release(self.button);
+        }
+    }
+}
+
+// `DeviceHandle::hold`, the release goes through the channel when the guard is dropped
+#[derive(Debug)]
+pub struct HandleHoldGuard {
+    handle: DeviceHandle,
+    button: Button,
+    released: bool,
+}
+
+impl HandleHoldGuard {
+    pub(crate) fn new(handle: DeviceHandle, button: Button) -> Self {
+        Self { handle, button, released: false }
+    }
+
+    #[inline]
+    pub fn button(&self) -> Button {
+        self.button
+    }
+
+    // releases now and reports the error that dropping the guard would ignore
+    pub fn release(mut self) -> EmptyResult {
+        self.released = true;
+        self.handle.release(self.button)
+    }
+}
+
+impl Deref for HandleHoldGuard {
+    type Target = DeviceHandle;
+
+    fn deref(&self) -> &Self::Target {
+        &self.handle
+    }
+}
+
+impl Drop for HandleHoldGuard {
+    fn drop(&mut self) {
+        if !self.released {
+            let _ = self.handle.release(self.button);
+        }
+    }
+}
+
+// `VirtualDevice::fling_scroll`, the fling stops when the guard is dropped.
+// scrolls through the guard stop it as well
+#[must_use = "the fling stops when the guard is dropped, `run` writes it to the end"]
+pub struct FlingGuard<'a> {
+    device: &'a mut VirtualDevice,
+}
+
+impl<'a> FlingGuard<'a> {
+    pub(crate) fn new(device: &'a mut VirtualDevice) -> Self {
+        Self { device }
+    }
+
+    // writes the distance covered since the previous tick, false once the fling has stopped
+    #[inline]
+    pub fn tick(&mut self) -> Result<bool> {
+        self.device.tick_fling()
+    }
+
+    // ticks at the motion rate of the device until the fling stops
+    pub fn run(mut self) -> EmptyResult {
+        let interval = Duration::from_secs_f64(1.0 / self.device.motion_rate() as f64);
+        while self.tick()? {
+            sleep(interval);
+        }
+        Ok(())
+    }
+
+    // leaves the fling running, `VirtualDevice::tick_fling` or the flush thread writes it
+    pub fn detach(self) {
+        mem::forget(self);
+    }
+}
+
+impl Deref for FlingGuard<'_> {
+    type Target = VirtualDevice;
+
+    fn deref(&self) -> &Self::Target {
+        self.device
+    }
+}
+
+impl DerefMut for FlingGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.device
+    }
+}
+
+impl Drop for FlingGuard<'_> {
+    fn drop(&mut self) {
+        self.device.cancel_fling();
+    }
+}
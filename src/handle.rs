@@ -0,0 +1,383 @@
+use std::ops::ControlFlow;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::scheduler::Wakeup;
+use crate::utils::{LineSteps, Rng};
+use crate::virtual_device::{plan_click, plan_motion, plan_multi_click, plan_type_text};
+use crate::{
+    Backpressure, Button, Capabilities, ChannelEvent, ChannelSender, Coord, Decay, DragOptions, Easing, EmptyResult, Frame,
+    HandleHoldGuard, HumanMotion, InputEvent, MotionFrames, Result, ScheduleId, Service, StopHandle, Timing, TimingProfile,
+    Trajectory, VirtualDevice,
+};
+
+#[derive(Debug)]
+struct HandleSettings {
+    timing: Timing,
+    human_motion: HumanMotion,
+    human_rng: Rng,
+    motion_rate: u32,
+    max_gradual_step: Coord,
+}
+
+// cloneable sender side of a device whose channel is flushed by `flush_channel_every_interval`
+// or `flush_channel_blocking`. clones share the timing and motion settings.
+// codes the device didn't register are rejected before anything is sent
+#[derive(Clone, Debug)]
+pub struct DeviceHandle {
+    sender: ChannelSender,
+    schedule_ids: Arc<AtomicU64>,
+    capabilities: Arc<Capabilities>,
+    settings: Arc<Mutex<HandleSettings>>,
+}
+
+impl DeviceHandle {
+    pub(crate) fn new(
+        sender: ChannelSender,
+        schedule_ids: Arc<AtomicU64>,
+        capabilities: Capabilities,
+        profile: TimingProfile,
+        human_motion: HumanMotion,
+        motion_rate: u32,
+        max_gradual_step: Coord,
+    ) -> Self {
+        let settings = HandleSettings {
+            timing: Timing::new(profile),
+            human_rng: Rng::new(human_motion.seed),
+            human_motion,
+            motion_rate,
+            max_gradual_step,
+        };
+        Self {
+            sender,
+            schedule_ids,
+            capabilities: Arc::new(capabilities),
+            settings: Arc::new(Mutex::new(settings)),
+        }
+    }
+
+    // a handle sharing the settings of this one but with its own policy and drop counter,
+    // e.g. `DropOldestMotion` for a sensor thread while the UI thread keeps blocking
+    pub fn with_backpressure(&self, backpressure: Backpressure) -> Self {
+        Self {
+            sender: self.sender.with_backpressure(backpressure),
+            schedule_ids: self.schedule_ids.clone(),
+            capabilities: self.capabilities.clone(),
+            settings: self.settings.clone(),
+        }
+    }
+
+    #[inline]
+    pub fn backpressure(&self) -> Backpressure {
+        self.sender.backpressure()
+    }
+
+    // events this handle and its clones discarded because the channel was full
+    #[inline]
+    pub fn dropped_events(&self) -> u64 {
+        self.sender.dropped()
+    }
+
+    #[inline]
+    pub fn channel_size(&self) -> usize {
+        self.sender.channel_size()
+    }
+
+    // what the device registered when the handle was created
+    #[inline]
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    #[inline]
+    pub(crate) fn shared_capabilities(&self) -> Arc<Capabilities> {
+        self.capabilities.clone()
+    }
+
+    #[inline]
+    pub fn send_frame(&self, frame: Frame) -> EmptyResult {
+        self.capabilities.ensure_frame(&frame)?;
+        VirtualDevice::send_frame(frame, &self.sender)
+    }
+
+    // nothing of the sequence is sent if one of its frames isn't supported
+    fn send_sequence(&self, frames: Vec<(Duration, Frame)>) -> EmptyResult {
+        for (_, frame) in &frames {
+            self.capabilities.ensure_frame(frame)?;
+        }
+        self.sender.send_sequence(frames)
+    }
+
+    // `tick` runs on the flush thread, see `ServiceTick`
+    pub(crate) fn spawn_service<F>(&self, wakeup: &Wakeup, tick: F) -> StopHandle
+    where
+        F: FnMut(Instant, &mut Vec<ChannelEvent>) -> ControlFlow<(), Option<Instant>> + Send + 'static,
+    {
+        Service::spawn(&self.sender, wakeup, tick)
+    }
+
+    // the flush thread writes the frame once `deadline` has passed, the caller doesn't wait
+    pub fn send_at(&self, deadline: Instant, frame: Frame) -> Result<ScheduleId> {
+        self.capabilities.ensure_frame(&frame)?;
+        let id = ScheduleId::next(&self.schedule_ids);
+        VirtualDevice::send_channel_event(ChannelEvent::Scheduled(id, deadline, frame), &self.sender)?;
+        Ok(id)
+    }
+
+    #[inline]
+    pub fn send_after(&self, delay: Duration, frame: Frame) -> Result<ScheduleId> {
+        self.send_at(Instant::now() + delay, frame)
+    }
+
+    // frames that were already written are not affected
+    #[inline]
+    pub fn cancel(&self, id: ScheduleId) -> EmptyResult {
+        VirtualDevice::send_channel_event(ChannelEvent::Cancel(id), &self.sender)
+    }
+
+    // all events end up in a single frame
+    pub fn emit(&self, events: &[InputEvent]) -> EmptyResult {
+        let frame = events.iter().fold(Frame::builder(), |builder, event| builder.event(*event));
+        self.send_frame(frame.build())
+    }
+
+    #[inline]
+    pub fn press(&self, button: impl Into<Button>) -> EmptyResult {
+        self.send_frame(Frame::builder().press(button).build())
+    }
+
+    #[inline]
+    pub fn release(&self, button: impl Into<Button>) -> EmptyResult {
+        self.send_frame(Frame::builder().release(button).build())
+    }
+
+    // the timing is planned under the settings lock, sending may block on a full channel without it
+    pub fn click(&self, button: impl Into<Button>) -> EmptyResult {
+        let hold = self.settings.lock().unwrap().timing.hold();
+        self.click_for(button, hold)
+    }
+
+    #[inline]
+    pub fn click_for(&self, button: impl Into<Button>, hold: Duration) -> EmptyResult {
+        self.send_sequence(plan_click(button.into(), hold))
+    }
+
+    #[inline]
+    pub fn double_click(&self, button: impl Into<Button>) -> EmptyResult {
+        self.multi_click(button, 2)
+    }
+
+    #[inline]
+    pub fn triple_click(&self, button: impl Into<Button>) -> EmptyResult {
+        self.multi_click(button, 3)
+    }
+
+    pub fn multi_click(&self, button: impl Into<Button>, count: usize) -> EmptyResult {
+        let frames = plan_multi_click(button.into(), count, &mut self.settings.lock().unwrap().timing);
+        self.send_sequence(frames)
+    }
+
+    // the guard derefs to the handle and sends the release when it is dropped
+    pub fn hold(&self, button: impl Into<Button>) -> Result<HandleHoldGuard> {
+        let button = button.into();
+        self.press(button)?;
+        Ok(HandleHoldGuard::new(self.clone(), button))
+    }
+
+    // the releases are sent in reverse order even if the action failed
+    pub fn with_modifiers<M, T, F>(&self, modifiers: &[M], action: F) -> Result<T>
+    where
+        M: Into<Button> + Copy,
+        F: FnOnce(&Self) -> Result<T>,
+    {
+        let mut held = Vec::with_capacity(modifiers.len());
+        let mut result = Ok(());
+
+        for &modifier in modifiers {
+            let modifier = modifier.into();
+            result = self.press(modifier);
+            if result.is_err() {
+                break;
+            }
+            held.push(modifier);
+        }
+
+        let result = result.and_then(|_| action(self));
+
+        let mut release_result = Ok(());
+        for &modifier in held.iter().rev() {
+            if let Err(err) = self.release(modifier) {
+                release_result = Err(err);
+            }
+        }
+        let value = result?;
+        release_result?;
+        Ok(value)
+    }
+
+    // like `VirtualDevice::drag` but scheduled on the flush thread, this returns right away
+    pub fn drag(&self, button: impl Into<Button>, x: Coord, y: Coord, options: &DragOptions) -> EmptyResult {
+        let button = button.into();
+        let (step_x, step_y) = options.threshold_step(x, y);
+        let motion = MotionFrames::new(x - step_x, y - step_y, options.duration, options.easing, self.motion_rate());
+
+        let mut frames = vec![(Duration::ZERO, Frame::builder().press(button).build())];
+        if step_x != 0 || step_y != 0 {
+            frames.push((options.start_delay, Frame::builder().move_by(step_x as f64, step_y as f64).build()));
+        }
+        frames.extend(plan_motion(motion).into_iter().map(|(offset, frame)| (options.start_delay + offset, frame)));
+
+        let release_at = options.start_delay + options.duration + options.end_delay;
+        frames.push((release_at, Frame::builder().release(button).build()));
+        self.send_sequence(frames)
+    }
+
+    pub fn type_text(&self, text: &str) -> EmptyResult {
+        let frames = plan_type_text(text, &mut self.settings.lock().unwrap().timing)?;
+        self.send_sequence(frames)
+    }
+
+    pub fn timing_profile(&self) -> TimingProfile {
+        self.settings.lock().unwrap().timing.profile().clone()
+    }
+
+    pub fn set_timing_profile(&self, profile: TimingProfile) {
+        self.settings.lock().unwrap().timing = Timing::new(profile);
+    }
+
+    #[inline]
+    pub fn move_mouse(&self, x: Coord, y: Coord) -> EmptyResult {
+        self.capabilities.ensure_motion()?;
+        VirtualDevice::send_mouse_move(x, y, &self.sender)
+    }
+
+    #[inline]
+    pub fn move_mouse_x(&self, x: Coord) -> EmptyResult {
+        self.capabilities.ensure_motion()?;
+        VirtualDevice::send_mouse_move_x(x, &self.sender)
+    }
+
+    #[inline]
+    pub fn move_mouse_y(&self, y: Coord) -> EmptyResult {
+        self.capabilities.ensure_motion()?;
+        VirtualDevice::send_mouse_move_y(y, &self.sender)
+    }
+
+    #[inline]
+    pub fn move_mouse_f(&self, x: f64, y: f64) -> EmptyResult {
+        self.capabilities.ensure_motion()?;
+        VirtualDevice::send_mouse_move_f(x, y, &self.sender)
+    }
+
+    // every step of at most `max_gradual_step` pixels goes out in its own frame
+    pub fn gradual_move_mouse(&self, x: Coord, y: Coord) -> EmptyResult {
+        for (step_x, step_y) in LineSteps::new(x, y, self.max_gradual_step()) {
+            self.move_mouse(step_x, step_y)?;
+        }
+        Ok(())
+    }
+
+    // all steps in a single frame, like `VirtualDevice::smooth_move_mouse`
+    pub fn smooth_move_mouse(&self, x: Coord, y: Coord) -> EmptyResult {
+        let frame = LineSteps::new(x, y, self.max_gradual_step())
+            .fold(Frame::builder(), |builder, (step_x, step_y)| builder.move_by(step_x as f64, step_y as f64));
+        self.send_frame(frame.build())
+    }
+
+    pub fn move_mouse_over(&self, x: Coord, y: Coord, duration: Duration, easing: Easing) -> EmptyResult {
+        let rate_hz = self.settings.lock().unwrap().motion_rate;
+        self.play_motion(MotionFrames::new(x, y, duration, easing, rate_hz))
+    }
+
+    pub fn move_mouse_human(&self, x: Coord, y: Coord) -> EmptyResult {
+        let trajectory = {
+            let mut settings = self.settings.lock().unwrap();
+            let seed = settings.human_rng.next_u64();
+            Trajectory::new(x, y, &settings.human_motion, seed)
+        };
+        self.play_motion(trajectory)
+    }
+
+    // frames are scheduled on the flush thread, this returns right away
+    #[inline]
+    pub fn play_motion(&self, frames: impl IntoIterator<Item = (Duration, Coord, Coord)>) -> EmptyResult {
+        self.capabilities.ensure_motion()?;
+        VirtualDevice::send_motion(frames, &self.sender)
+    }
+
+    pub fn human_motion(&self) -> HumanMotion {
+        self.settings.lock().unwrap().human_motion.clone()
+    }
+
+    pub fn set_human_motion(&self, motion: HumanMotion) {
+        let mut settings = self.settings.lock().unwrap();
+        settings.human_rng = Rng::new(motion.seed);
+        settings.human_motion = motion;
+    }
+
+    pub fn motion_rate(&self) -> u32 {
+        self.settings.lock().unwrap().motion_rate
+    }
+
+    pub fn set_motion_rate(&self, rate_hz: u32) {
+        self.settings.lock().unwrap().motion_rate = rate_hz.max(1);
+    }
+
+    pub fn max_gradual_step(&self) -> Coord {
+        self.settings.lock().unwrap().max_gradual_step
+    }
+
+    pub fn set_max_gradual_step(&self, max_step: Coord) {
+        self.settings.lock().unwrap().max_gradual_step = max_step.max(1);
+    }
+
+    #[inline]
+    pub fn scroll(&self, x: Coord, y: Coord) -> EmptyResult {
+        self.capabilities.ensure_scroll()?;
+        VirtualDevice::send_scroll_f(x as f64, y as f64, &self.sender)
+    }
+
+    #[inline]
+    pub fn scroll_x(&self, value: Coord) -> EmptyResult {
+        self.capabilities.ensure_scroll()?;
+        VirtualDevice::send_scroll_x(value, &self.sender)
+    }
+
+    #[inline]
+    pub fn scroll_y(&self, value: Coord) -> EmptyResult {
+        self.capabilities.ensure_scroll()?;
+        VirtualDevice::send_scroll_y(value, &self.sender)
+    }
+
+    #[inline]
+    pub fn scroll_f(&self, x: f64, y: f64) -> EmptyResult {
+        self.capabilities.ensure_scroll()?;
+        VirtualDevice::send_scroll_f(x, y, &self.sender)
+    }
+
+    pub fn gradual_scroll(&self, x: Coord, y: Coord) -> EmptyResult {
+        for (step_x, step_y) in LineSteps::new(x, y, self.max_gradual_step()) {
+            self.scroll(step_x, step_y)?;
+        }
+        Ok(())
+    }
+
+    pub fn smooth_scroll(&self, x: Coord, y: Coord) -> EmptyResult {
+        let frame = LineSteps::new(x, y, self.max_gradual_step())
+            .fold(Frame::builder(), |builder, (step_x, step_y)| builder.scroll(step_x as f64, step_y as f64));
+        self.send_frame(frame.build())
+    }
+
+    #[inline]
+    pub fn fling_scroll(&self, velocity_x: f64, velocity_y: f64, friction: f64) -> EmptyResult {
+        self.capabilities.ensure_scroll()?;
+        VirtualDevice::send_fling_scroll(velocity_x, velocity_y, friction, &self.sender)
+    }
+
+    #[inline]
+    pub fn fling_scroll_with(&self, velocity_x: f64, velocity_y: f64, decay: Decay) -> EmptyResult {
+        self.capabilities.ensure_scroll()?;
+        VirtualDevice::send_fling_scroll_with(velocity_x, velocity_y, decay, &self.sender)
+    }
+}
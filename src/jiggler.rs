@@ -0,0 +1,109 @@
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+use crate::scheduler::Wakeup;
+use crate::{ChannelEvent, Coord, DeviceHandle, Frame, Key, Result, StopHandle, Timing};
+use crate::key_codes::KEY_F24;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JiggleMode {
+    // moves right by this many pixels and back, the cursor ends up where it was
+    Move(Coord),
+    // clicks a key that applications don't react to
+    Key(Key),
+}
+
+#[derive(Clone, Debug)]
+pub struct JigglerSettings {
+    pub interval: Duration,
+    pub mode: JiggleMode,
+    // delay before the pointer moves back in `JiggleMode::Move`
+    pub return_after: Duration,
+}
+
+impl Default for JigglerSettings {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            mode: JiggleMode::Move(1),
+            return_after: Duration::from_millis(50),
+        }
+    }
+}
+
+impl JigglerSettings {
+    pub fn no_op_key() -> Self {
+        Self {
+            mode: JiggleMode::Key(Key(KEY_F24)),
+            ..Self::default()
+        }
+    }
+}
+
+// keeps the session awake, the first jiggle happens after one interval.
+// runs on the flush thread of the device, which sleeps until the next jiggle.
+// fails if the device can't write the jiggle, instead of jiggling without effect
+pub fn spawn_jiggler(settings: JigglerSettings, device: DeviceHandle) -> Result<StopHandle> {
+    let mut timing = Timing::new(device.timing_profile());
+    for (_, frame) in jiggle_frames(&settings, Duration::ZERO) {
+        device.capabilities().ensure_frame(&frame)?;
+    }
+    let mut next_jiggle = Instant::now() + settings.interval;
+
+    Ok(device.spawn_service(&Wakeup::default(), move |now, events| {
+        if now >= next_jiggle {
+            let [(_, there), (after, back)] = jiggle_frames(&settings, timing.hold());
+            events.push(ChannelEvent::Now(there));
+            events.push(ChannelEvent::At(now + after, back));
+            next_jiggle = now + settings.interval;
+        }
+        ControlFlow::Continue(Some(next_jiggle))
+    }))
+}
+
+// there and back again, each with its offset from the start of the jiggle.
+// the no-op key is held for `hold`, from the timing profile of the handle
+fn jiggle_frames(settings: &JigglerSettings, hold: Duration) -> [(Duration, Frame); 2] {
+    match settings.mode {
+        // both moves land in separate frames
+        JiggleMode::Move(distance) => [
+            (Duration::ZERO, Frame::builder().move_by(distance as f64, 0.0).build()),
+            (settings.return_after, Frame::builder().move_by(-distance as f64, 0.0).build()),
+        ],
+        JiggleMode::Key(key) => [
+            (Duration::ZERO, Frame::builder().press(key).build()),
+            (hold, Frame::builder().release(key).build()),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jiggle_once(mode: JiggleMode) -> (Frame, Duration, Frame) {
+        let settings = JigglerSettings { mode, return_after: Duration::from_millis(20), ..JigglerSettings::default() };
+        let [(start, there), (after, back)] = jiggle_frames(&settings, Duration::from_millis(30));
+        assert_eq!(start, Duration::ZERO);
+        (there, after, back)
+    }
+
+    #[test]
+    fn moves_and_returns_in_separate_frames() {
+        let (there, after, back) = jiggle_once(JiggleMode::Move(3));
+
+        assert_eq!(there, Frame::builder().move_by(3.0, 0.0).build());
+        assert_eq!(after, Duration::from_millis(20));
+        assert_eq!(back, Frame::builder().move_by(-3.0, 0.0).build());
+    }
+
+    #[test]
+    fn no_op_key_clicks_f24() {
+        let settings = JigglerSettings::no_op_key();
+        let (there, after, back) = jiggle_once(settings.mode);
+
+        assert_eq!(there, Frame::builder().press(KEY_F24).build());
+        assert_eq!(after, Duration::from_millis(30));
+        assert_eq!(back, Frame::builder().release(KEY_F24).build());
+    }
+}
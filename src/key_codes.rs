@@ -644,10 +644,18 @@ pub const REL_Z:      u16 = 0x02;
 pub const REL_RX:     u16 = 0x03;
 pub const REL_RY:     u16 = 0x04;
 pub const REL_RZ:     u16 = 0x05;
+#[deprecated(note = "REL_HWHEEL is the hi-res wheel here, use REL_HWHEEL_HI_RES, or REL_HWHEEL_NOTCH for the kernel's REL_HWHEEL")]
 pub const REL_HWHEEL: u16 = 12; // high resolution wheel
 pub const REL_DIAL:   u16 = 0x07;
+#[deprecated(note = "REL_WHEEL is the hi-res wheel here, use REL_WHEEL_HI_RES, or REL_WHEEL_NOTCH for the kernel's REL_WHEEL")]
 pub const REL_WHEEL:  u16 = 11; // high resolution wheel
 pub const REL_MISC:   u16 = 0x09;
+pub const REL_RESERVED:       u16 = 0x0a;
+pub const REL_WHEEL_HI_RES:   u16 = 0x0b; // 120 units per notch of REL_WHEEL_NOTCH
+pub const REL_HWHEEL_HI_RES:  u16 = 0x0c;
+// REL_WHEEL and REL_HWHEEL of the kernel, the names above keep their old hi-res values
+pub const REL_HWHEEL_NOTCH:   u16 = 0x06;
+pub const REL_WHEEL_NOTCH:    u16 = 0x08;
 pub const REL_MAX:    u16 = 0x0f;
 pub const REL_CNT:    u16 = REL_MAX + 1;
 
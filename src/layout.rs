@@ -0,0 +1,102 @@
+use crate::key_codes::*;
+use crate::Key;
+
+// US QWERTY layout: returns the key and whether Shift has to be held
+pub fn char_to_key(c: char) -> Option<(Key, bool)> {
+    let (code, shift) = match c {
+        'a'..='z' => (letter_code(c)?, false),
+        'A'..='Z' => (letter_code(c.to_ascii_lowercase())?, true),
+
+        '1' => (KEY_1, false),
+        '2' => (KEY_2, false),
+        '3' => (KEY_3, false),
+        '4' => (KEY_4, false),
+        '5' => (KEY_5, false),
+        '6' => (KEY_6, false),
+        '7' => (KEY_7, false),
+        '8' => (KEY_8, false),
+        '9' => (KEY_9, false),
+        '0' => (KEY_10, false),
+
+        '!' => (KEY_1, true),
+        '@' => (KEY_2, true),
+        '#' => (KEY_3, true),
+        '$' => (KEY_4, true),
+        '%' => (KEY_5, true),
+        '^' => (KEY_6, true),
+        '&' => (KEY_7, true),
+        '*' => (KEY_8, true),
+        '(' => (KEY_9, true),
+        ')' => (KEY_10, true),
+
+        '-' => (KEY_MINUS, false),
+        '_' => (KEY_MINUS, true),
+        '=' => (KEY_EQUAL, false),
+        '+' => (KEY_EQUAL, true),
+        '[' => (KEY_LEFTBRACE, false),
+        '{' => (KEY_LEFTBRACE, true),
+        ']' => (KEY_RIGHTBRACE, false),
+        '}' => (KEY_RIGHTBRACE, true),
+        '\\' => (KEY_BACKSLASH, false),
+        '|' => (KEY_BACKSLASH, true),
+        ';' => (KEY_SEMICOLON, false),
+        ':' => (KEY_SEMICOLON, true),
+        '\'' => (KEY_APOSTROPHE, false),
+        '"' => (KEY_APOSTROPHE, true),
+        '`' => (KEY_GRAVE, false),
+        '~' => (KEY_GRAVE, true),
+        ',' => (KEY_COMMA, false),
+        '<' => (KEY_COMMA, true),
+        '.' => (KEY_DOT, false),
+        '>' => (KEY_DOT, true),
+        '/' => (KEY_SLASH, false),
+        '?' => (KEY_SLASH, true),
+
+        ' ' => (KEY_SPACE, false),
+        '\t' => (KEY_TAB, false),
+        '\n' => (KEY_ENTER, false),
+        _ => return None,
+    };
+    Some((Key(code), shift))
+}
+
+fn letter_code(c: char) -> Option<u16> {
+    let code = match c {
+        'a' => KEY_A,
+        'b' => KEY_B,
+        'c' => KEY_C,
+        'd' => KEY_D,
+        'e' => KEY_E,
+        'f' => KEY_F,
+        'g' => KEY_G,
+        'h' => KEY_H,
+        'i' => KEY_I,
+        'j' => KEY_J,
+        'k' => KEY_K,
+        'l' => KEY_L,
+        'm' => KEY_M,
+        'n' => KEY_N,
+        'o' => KEY_O,
+        'p' => KEY_P,
+        'q' => KEY_Q,
+        'r' => KEY_R,
+        's' => KEY_S,
+        't' => KEY_T,
+        'u' => KEY_U,
+        'v' => KEY_V,
+        'w' => KEY_W,
+        'x' => KEY_X,
+        'y' => KEY_Y,
+        'z' => KEY_Z,
+        _ => return None,
+    };
+    Some(code)
+}
+
+pub fn text_to_keys(text: &str) -> crate::Result<Vec<(Key, bool)>> {
+    text.chars()
+        .map(|c| char_to_key(c).ok_or_else(|| {
+            Box::from(format!("Character {:?} can't be typed with the US layout", c))
+        }))
+        .collect()
+}
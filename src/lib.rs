@@ -10,8 +10,10 @@ use std::mem;
 #[cfg(target_arch = "arm")]
 macro_rules! uin {
 	(write $name:ident with $ioty:expr, $nr:expr; $ty:ty) => (
+		/// # Safety
+		/// `fd` must be an open uinput file descriptor.
 		pub unsafe fn $name(fd: i32, val: $ty) -> i32 {
-            ioctl::ioctl(fd, (iow!($ioty, $nr, mem::size_of::<$ty>()) as u64).try_into().unwrap(), val)
+            unsafe { ioctl::ioctl(fd, (iow!($ioty, $nr, mem::size_of::<$ty>()) as u64).try_into().unwrap(), val) }
 		}
 	);
 }
@@ -19,19 +21,63 @@ macro_rules! uin {
 #[cfg(not(target_arch = "arm"))]
 macro_rules! uin {
 	(write $name:ident with $ioty:expr, $nr:expr; $ty:ty) => (
+		/// # Safety
+		/// `fd` must be an open uinput file descriptor.
 		pub unsafe fn $name(fd: i32, val: $ty) -> i32 {
-			ioctl::ioctl(fd, iow!($ioty, $nr, mem::size_of::<$ty>()) as u64, val)
+			unsafe { ioctl::ioctl(fd, iow!($ioty, $nr, mem::size_of::<$ty>()) as u64, val) }
 		}
 	);
 }
 
 
 pub mod key_codes;
+#[cfg(feature = "async")]
+mod async_device;
+mod channel;
+mod code_names;
+mod codes;
+mod coalesce;
+mod coords;
+mod fling;
+mod flush;
+mod frame;
+mod geometry;
+mod guard;
+mod handle;
+mod jiggler;
+mod layout;
+mod motion;
+mod mouse_keys;
+mod pointer;
+mod scheduler;
+mod timing;
+mod trajectory;
 mod virtual_device;
 mod utils;
+mod writer;
 
 pub use crate::key_codes::*;
+#[cfg(feature = "async")]
+pub use async_device::*;
+pub use channel::{Backpressure, ChannelFull, ChannelSender};
+pub use codes::*;
+pub use coords::*;
+pub use fling::*;
+pub use flush::*;
+pub use frame::*;
+pub use geometry::*;
+pub use guard::*;
+pub use handle::*;
+pub use jiggler::*;
+pub use layout::*;
+pub use motion::*;
+pub use mouse_keys::*;
+pub use pointer::*;
+pub use scheduler::{Service, StopHandle};
+pub use timing::*;
+pub use trajectory::*;
 pub use virtual_device::*;
+pub use writer::{DEFAULT_WRITE_TIMEOUT, EventWriter, WriteError};
 
 pub const UINPUT_MAX_NAME_SIZE: usize = 80;
 
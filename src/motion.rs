@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use crate::Coord;
+
+pub const DEFAULT_MOTION_RATE: u32 = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseInOut,
+    // same control points as CSS `cubic-bezier(x1, y1, x2, y2)`
+    CubicBezier(f64, f64, f64, f64),
+}
+
+impl Easing {
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Easing::CubicBezier(x1, y1, x2, y2) => {
+                let s = solve_bezier_parameter(t, x1, x2);
+                bezier_coordinate(s, y1, y2)
+            }
+        }
+    }
+}
+
+#[inline]
+fn bezier_coordinate(s: f64, p1: f64, p2: f64) -> f64 {
+    let inv = 1.0 - s;
+    3.0 * inv * inv * s * p1 + 3.0 * inv * s * s * p2 + s * s * s
+}
+
+// finds the curve parameter whose x coordinate equals `x`, x(s) is monotonic for x1, x2 in [0, 1]
+fn solve_bezier_parameter(x: f64, x1: f64, x2: f64) -> f64 {
+    let (mut low, mut high) = (0.0, 1.0);
+    let mut s = x;
+
+    for _ in 0..32 {
+        let current = bezier_coordinate(s, x1, x2);
+        if (current - x).abs() < 1e-6 {
+            break;
+        }
+        if current < x {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+    s
+}
+
+// relative mouse steps spread over `duration`, yields (offset from start, dx, dy)
+#[derive(Clone, Debug)]
+pub struct MotionFrames {
+    x: Coord,
+    y: Coord,
+    easing: Easing,
+    frame_count: u32,
+    frame_interval: Duration,
+    index: u32,
+    emitted_x: Coord,
+    emitted_y: Coord,
+}
+
+impl MotionFrames {
+    pub fn new(x: Coord, y: Coord, duration: Duration, easing: Easing, rate_hz: u32) -> Self {
+        let rate_hz = rate_hz.max(1);
+        let frame_count = (duration.as_secs_f64() * rate_hz as f64).ceil().max(1.0) as u32;
+
+        Self {
+            x,
+            y,
+            easing,
+            frame_count,
+            frame_interval: Duration::from_secs(1) / rate_hz,
+            index: 0,
+            emitted_x: 0,
+            emitted_y: 0,
+        }
+    }
+}
+
+impl Iterator for MotionFrames {
+    type Item = (Duration, Coord, Coord);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.frame_count {
+            self.index += 1;
+
+            let progress = self.easing.apply(self.index as f64 / self.frame_count as f64);
+            let target_x = (self.x as f64 * progress).round() as Coord;
+            let target_y = (self.y as f64 * progress).round() as Coord;
+
+            let dx = target_x - self.emitted_x;
+            let dy = target_y - self.emitted_y;
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+
+            self.emitted_x = target_x;
+            self.emitted_y = target_y;
+            return Some((self.frame_interval * (self.index - 1), dx, dy));
+        }
+        None
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DragOptions {
+    // time between the press and the first motion, lets the toolkit process the press
+    pub start_delay: Duration,
+    pub duration: Duration,
+    pub easing: Easing,
+    // time between the last motion and the release, lets the drop target register the hover
+    pub end_delay: Duration,
+    // distance toolkits wait for before they start a drag, 8 pixels in GTK. the pointer first steps
+    // past it on the longer axis, or all the way when the drag is shorter. zero turns the step off
+    pub threshold: Coord,
+}
+
+impl Default for DragOptions {
+    fn default() -> Self {
+        Self {
+            start_delay: Duration::from_millis(80),
+            duration: Duration::from_millis(250),
+            easing: Easing::EaseInOut,
+            end_delay: Duration::from_millis(80),
+            threshold: 8,
+        }
+    }
+}
+
+impl DragOptions {
+    // first move of a drag by (x, y): one unit past the threshold on the longer axis, toward (x, y).
+    // drags that don't get that far go straight to the end, the step never overshoots
+    pub(crate) fn threshold_step(&self, x: Coord, y: Coord) -> (Coord, Coord) {
+        let longer = x.abs().max(y.abs());
+        if self.threshold <= 0 || longer == 0 {
+            return (0, 0);
+        }
+
+        let scale = (self.threshold + 1).min(longer) as f64 / longer as f64;
+        ((x as f64 * scale).round() as Coord, (y as f64 * scale).round() as Coord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASINGS: [Easing; 4] = [
+        Easing::Linear,
+        Easing::EaseInOut,
+        Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+        Easing::CubicBezier(0.0, 0.0, 1.0, 1.0),
+    ];
+
+    #[test]
+    fn easings_start_at_zero_and_end_at_one() {
+        for easing in EASINGS {
+            assert!(easing.apply(0.0).abs() < 1e-6, "{easing:?}");
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{easing:?}");
+            // out of range progress is clamped
+            assert_eq!(easing.apply(-1.0), easing.apply(0.0));
+            assert_eq!(easing.apply(2.0), easing.apply(1.0));
+        }
+    }
+
+    #[test]
+    fn motion_frames_sum_exactly_to_the_target() {
+        for easing in EASINGS {
+            for (x, y) in [(100, 0), (-37, 251), (3, -2), (0, 0), (1, 1000)] {
+                let frames: Vec<_> = MotionFrames::new(x, y, Duration::from_millis(300), easing, 120).collect();
+                let sum = frames.iter().fold((0, 0), |(sx, sy), (_, dx, dy)| (sx + dx, sy + dy));
+
+                assert_eq!(sum, (x, y), "{easing:?}");
+                assert!(frames.iter().all(|(_, dx, dy)| *dx != 0 || *dy != 0));
+                assert!(frames.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            }
+        }
+    }
+
+    #[test]
+    fn motion_frames_are_spread_over_the_duration() {
+        let frames: Vec<_> = MotionFrames::new(1000, 0, Duration::from_millis(100), Easing::Linear, 100).collect();
+
+        assert_eq!(frames.len(), 10);
+        assert_eq!(frames[0].0, Duration::ZERO);
+        assert_eq!(frames[9].0, Duration::from_millis(90));
+    }
+
+    #[test]
+    fn threshold_step_never_goes_past_the_drag() {
+        let options = DragOptions::default();
+
+        assert_eq!(options.threshold_step(100, 50), (9, 5));
+        assert_eq!(options.threshold_step(-20, 0), (-9, 0));
+        assert_eq!(options.threshold_step(3, 0), (3, 0));
+        assert_eq!(options.threshold_step(-2, 4), (-2, 4));
+        assert_eq!(options.threshold_step(0, 0), (0, 0));
+        assert_eq!(DragOptions { threshold: 0, ..options }.threshold_step(100, 0), (0, 0));
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::BTreeSet;
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::key_codes::*;
+use crate::scheduler::Wakeup;
+use crate::virtual_device::plan_click;
+use crate::{Button, Capabilities, ChannelEvent, Coord, DeviceHandle, EmptyResult, Frame, Key, Result, StopHandle, Timing};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    fn key(self) -> Key {
+        Key(match self {
+            Direction::Up => KEY_UP,
+            Direction::Down => KEY_DOWN,
+            Direction::Left => KEY_LEFT,
+            Direction::Right => KEY_RIGHT,
+        })
+    }
+}
+
+// arrows and the numeric keypad, y grows up
+fn key_direction(key: Key) -> Option<(i8, i8)> {
+    let direction = match key.code() {
+        KEY_UP | KEY_KP8 => (0, 1),
+        KEY_DOWN | KEY_KP2 => (0, -1),
+        KEY_LEFT | KEY_KP4 => (-1, 0),
+        KEY_RIGHT | KEY_KP6 => (1, 0),
+        KEY_KP7 => (-1, 1),
+        KEY_KP9 => (1, 1),
+        KEY_KP1 => (-1, -1),
+        KEY_KP3 => (1, -1),
+        _ => return None,
+    };
+    Some(direction)
+}
+
+#[derive(Clone, Debug)]
+pub struct MouseKeysSettings {
+    // pixels per second right after a direction key goes down
+    pub initial_speed: f64,
+    pub max_speed: f64,
+    // how long a direction has to be held to reach `max_speed`
+    pub time_to_max: Duration,
+    // button clicked by keypad 5
+    pub button: Button,
+}
+
+impl Default for MouseKeysSettings {
+    fn default() -> Self {
+        Self {
+            initial_speed: 100.0,
+            max_speed: 1200.0,
+            time_to_max: Duration::from_millis(1500),
+            button: Key::BTN_LEFT,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MouseKeysState {
+    held: BTreeSet<Key>,
+    // None clicks `MouseKeysSettings::button`
+    clicks: Vec<Option<Button>>,
+    // of the device the engine was spawned on
+    capabilities: Option<Arc<Capabilities>>,
+}
+
+// key states fed from any thread, e.g. a keyboard grabbed with evdev
+#[derive(Clone, Debug, Default)]
+pub struct MouseKeysInput {
+    state: Arc<Mutex<MouseKeysState>>,
+    wakeup: Arc<Wakeup>,
+}
+
+impl MouseKeysInput {
+    #[inline]
+    pub fn set_direction(&self, direction: Direction, pressed: bool) {
+        self.handle_key(direction.key(), pressed);
+    }
+
+    // fails once the engine is spawned if its device doesn't have the button
+    pub fn click(&self, button: impl Into<Button>) -> EmptyResult {
+        let button = button.into();
+        let mut state = self.state.lock().unwrap();
+        if let Some(capabilities) = &state.capabilities {
+            capabilities.ensure_key(button)?;
+        }
+        state.clicks.push(Some(button));
+        self.wakeup.notify();
+        Ok(())
+    }
+
+    // returns false for keys that mouse keys doesn't use so they can be passed through
+    pub fn handle_key(&self, key: impl Into<Key>, pressed: bool) -> bool {
+        let key = key.into();
+        let mut state = self.state.lock().unwrap();
+
+        if key.code() == KEY_KP5 {
+            if pressed {
+                state.clicks.push(None);
+                self.wakeup.notify();
+            }
+            return true;
+        }
+        if key_direction(key).is_none() {
+            return false;
+        }
+
+        if pressed {
+            state.held.insert(key);
+        } else {
+            state.held.remove(&key);
+        }
+        self.wakeup.notify();
+        true
+    }
+
+    fn direction(&self) -> (f64, f64) {
+        let state = self.state.lock().unwrap();
+        let (x, y) = state.held.iter()
+            .filter_map(|&key| key_direction(key))
+            .fold((0, 0), |(x, y), (dx, dy)| (x + dx as i32, y + dy as i32));
+        (x.signum() as f64, y.signum() as f64)
+    }
+
+    fn take_clicks(&self) -> Vec<Option<Button>> {
+        std::mem::take(&mut self.state.lock().unwrap().clicks)
+    }
+}
+
+#[derive(Debug)]
+pub struct MouseKeys {
+    settings: MouseKeysSettings,
+    input: MouseKeysInput,
+    held_for: Duration,
+    remainder: (f64, f64),
+}
+
+impl MouseKeys {
+    pub fn new(settings: MouseKeysSettings) -> Self {
+        Self {
+            settings,
+            input: MouseKeysInput::default(),
+            held_for: Duration::ZERO,
+            remainder: (0.0, 0.0),
+        }
+    }
+
+    #[inline]
+    pub fn input(&self) -> MouseKeysInput {
+        self.input.clone()
+    }
+
+    #[inline]
+    pub fn settings(&self) -> &MouseKeysSettings {
+        &self.settings
+    }
+
+    #[inline]
+    fn speed(&self) -> f64 {
+        let progress = match self.settings.time_to_max.is_zero() {
+            true => 1.0,
+            false => (self.held_for.as_secs_f64() / self.settings.time_to_max.as_secs_f64()).min(1.0),
+        };
+        self.settings.initial_speed + (self.settings.max_speed - self.settings.initial_speed) * progress
+    }
+
+    // advances the engine by `elapsed` and returns whole pixels to move, y grows up
+    // as with the default `CoordinateSystem::Y_UP`
+    pub fn tick(&mut self, elapsed: Duration) -> (Coord, Coord) {
+        let (x, y) = self.input.direction();
+
+        if x == 0.0 && y == 0.0 {
+            self.held_for = Duration::ZERO;
+            self.remainder = (0.0, 0.0);
+            return (0, 0);
+        }
+        self.held_for += elapsed;
+
+        // diagonals move as fast as straight lines
+        let distance = self.speed() * elapsed.as_secs_f64() / x.hypot(y);
+        let x = x * distance + self.remainder.0;
+        let y = y * distance + self.remainder.1;
+        let (whole_x, whole_y) = (x.trunc(), y.trunc());
+        self.remainder = (x - whole_x, y - whole_y);
+
+        (whole_x as Coord, whole_y as Coord)
+    }
+
+    // ticks every `interval` on the flush thread of the device while a direction is held
+    // and sleeps until the next key otherwise. clicks hold for the timing profile of the handle.
+    // fails if the device has no pointer or no `MouseKeysSettings::button`
+    pub fn spawn(mut self, interval: Duration, device: DeviceHandle) -> Result<StopHandle> {
+        device.capabilities().ensure_motion()?;
+        device.capabilities().ensure_key(self.settings.button)?;
+        self.input.state.lock().unwrap().capabilities = Some(device.shared_capabilities());
+
+        let mut timing = Timing::new(device.timing_profile());
+        let wakeup = self.input.wakeup.clone();
+        let mut last_tick = None;
+
+        Ok(device.spawn_service(&wakeup, move |now, events| {
+            let (x, y) = self.tick(last_tick.map_or(Duration::ZERO, |last_tick| now - last_tick));
+
+            // one click after the other, each released before the next one is pressed
+            let mut offset = Duration::ZERO;
+            for button in self.input.take_clicks() {
+                let button = button.unwrap_or(self.settings.button);
+                let hold = timing.hold();
+                for (at, frame) in plan_click(button, hold) {
+                    events.push(ChannelEvent::At(now + offset + at, frame));
+                }
+                offset += hold;
+            }
+            if x != 0 || y != 0 {
+                events.push(ChannelEvent::Now(Frame::builder().move_by(x as f64, y as f64).build()));
+            }
+            last_tick = (self.input.direction() != (0.0, 0.0)).then_some(now);
+            ControlFlow::Continue(last_tick.map(|last_tick| last_tick + interval))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(250);
+
+    fn mouse_keys(initial_speed: f64, max_speed: f64, time_to_max: Duration) -> MouseKeys {
+        MouseKeys::new(MouseKeysSettings { initial_speed, max_speed, time_to_max, ..MouseKeysSettings::default() })
+    }
+
+    #[test]
+    fn speeds_up_from_initial_to_max_speed() {
+        let mut mouse_keys = mouse_keys(400.0, 2000.0, Duration::from_secs(1));
+        let input = mouse_keys.input();
+
+        input.set_direction(Direction::Up, true);
+        // 800, 1200, 1600 and 2000 pixels per second after each quarter of a second
+        let moves: Vec<Coord> = (0..6).map(|_| mouse_keys.tick(TICK).1).collect();
+        assert_eq!(moves, [200, 300, 400, 500, 500, 500]);
+
+        // releasing the key starts over from the initial speed
+        input.set_direction(Direction::Up, false);
+        assert_eq!(mouse_keys.tick(TICK), (0, 0));
+        input.set_direction(Direction::Left, true);
+        assert_eq!(mouse_keys.tick(TICK), (-200, 0));
+    }
+
+    #[test]
+    fn diagonals_are_as_fast_as_straight_lines() {
+        let mut mouse_keys = mouse_keys(1000.0, 1000.0, Duration::ZERO);
+        let input = mouse_keys.input();
+
+        input.handle_key(KEY_KP9, true);
+        assert_eq!(mouse_keys.tick(TICK), (176, 176));
+        input.handle_key(KEY_KP9, false);
+
+        input.set_direction(Direction::Down, true);
+        input.set_direction(Direction::Left, true);
+        assert_eq!(mouse_keys.tick(TICK), (-176, -176));
+
+        // opposite directions cancel each other out
+        input.set_direction(Direction::Right, true);
+        assert_eq!(mouse_keys.tick(TICK), (0, -250));
+    }
+
+    #[test]
+    fn keypad_5_clicks_the_configured_button() {
+        let input = MouseKeysInput::default();
+
+        assert!(input.handle_key(KEY_KP5, true));
+        assert!(input.handle_key(KEY_KP5, false));
+        input.click(Key::BTN_RIGHT).unwrap();
+
+        assert_eq!(input.take_clicks(), [None, Some(Key::BTN_RIGHT)]);
+        assert!(input.take_clicks().is_empty());
+    }
+
+    #[test]
+    fn other_keys_are_passed_through() {
+        let input = MouseKeysInput::default();
+
+        assert!(!input.handle_key(KEY_A, true));
+        assert!(!input.handle_key(KEY_KPENTER, true));
+        assert!(input.handle_key(KEY_KP1, true));
+        assert!(input.handle_key(KEY_RIGHT, true));
+
+        assert_eq!(input.direction(), (0.0, -1.0));
+        assert!(input.take_clicks().is_empty());
+    }
+}
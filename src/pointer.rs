@@ -0,0 +1,253 @@
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::scheduler::Wakeup;
+use crate::{ChannelEvent, Coord, DeviceHandle, Frame, Result, StopHandle};
+
+#[derive(Clone, Copy, Debug)]
+pub enum ResponseCurve {
+    Linear,
+    Quadratic,
+    // maps input magnitude in [0, 1] to speed fraction in [0, 1]
+    Custom(fn(f64) -> f64),
+}
+
+impl ResponseCurve {
+    pub fn apply(&self, magnitude: f64) -> f64 {
+        let magnitude = magnitude.clamp(0.0, 1.0);
+        match self {
+            ResponseCurve::Linear => magnitude,
+            ResponseCurve::Quadratic => magnitude * magnitude,
+            ResponseCurve::Custom(curve) => curve(magnitude).clamp(0.0, 1.0),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PointerSettings {
+    // input magnitudes below this are ignored, the rest of the range is rescaled to [0, 1]
+    pub dead_zone: f64,
+    pub curve: ResponseCurve,
+    // pixels per second at full deflection
+    pub max_speed: f64,
+    // pixels per second squared, zero reaches the target speed immediately
+    pub acceleration: f64,
+}
+
+impl Default for PointerSettings {
+    fn default() -> Self {
+        Self {
+            dead_zone: 0.15,
+            curve: ResponseCurve::Quadratic,
+            max_speed: 1500.0,
+            acceleration: 6000.0,
+        }
+    }
+}
+
+// latest normalised stick position, can be updated from any thread at any rate
+#[derive(Clone, Debug, Default)]
+pub struct PointerInput {
+    value: Arc<Mutex<(f64, f64)>>,
+    wakeup: Arc<Wakeup>,
+}
+
+impl PointerInput {
+    // x grows to the right and y grows up, both in [-1, 1]
+    pub fn set(&self, x: f64, y: f64) {
+        *self.value.lock().unwrap() = (x.clamp(-1.0, 1.0), y.clamp(-1.0, 1.0));
+        self.wakeup.notify();
+    }
+
+    pub fn get(&self) -> (f64, f64) {
+        *self.value.lock().unwrap()
+    }
+}
+
+#[derive(Debug)]
+pub struct PointerController {
+    settings: PointerSettings,
+    input: PointerInput,
+    velocity: (f64, f64),
+    remainder: (f64, f64),
+}
+
+impl PointerController {
+    pub fn new(settings: PointerSettings) -> Self {
+        Self {
+            settings,
+            input: PointerInput::default(),
+            velocity: (0.0, 0.0),
+            remainder: (0.0, 0.0),
+        }
+    }
+
+    #[inline]
+    pub fn input(&self) -> PointerInput {
+        self.input.clone()
+    }
+
+    #[inline]
+    pub fn set_input(&self, x: f64, y: f64) {
+        self.input.set(x, y);
+    }
+
+    #[inline]
+    pub fn settings(&self) -> &PointerSettings {
+        &self.settings
+    }
+
+    fn target_velocity(&self) -> (f64, f64) {
+        let (x, y) = self.input.get();
+        let magnitude = x.hypot(y);
+        let dead_zone = self.settings.dead_zone.clamp(0.0, 0.99);
+
+        if magnitude <= dead_zone {
+            return (0.0, 0.0);
+        }
+
+        let scaled = ((magnitude - dead_zone) / (1.0 - dead_zone)).min(1.0);
+        let speed = self.settings.curve.apply(scaled) * self.settings.max_speed;
+        (x / magnitude * speed, y / magnitude * speed)
+    }
+
+    // advances the controller by `elapsed` and returns whole pixels to move, y grows up.
+    // `spawn` sends them through the coordinate system of the device like any other move
+    pub fn tick(&mut self, elapsed: Duration) -> (Coord, Coord) {
+        let dt = elapsed.as_secs_f64();
+        let target = self.target_velocity();
+
+        let max_change = self.settings.acceleration * dt;
+        let change = (target.0 - self.velocity.0, target.1 - self.velocity.1);
+        let change_length = change.0.hypot(change.1);
+
+        if self.settings.acceleration <= 0.0 || change_length <= max_change {
+            self.velocity = target;
+        } else {
+            let scale = max_change / change_length;
+            self.velocity.0 += change.0 * scale;
+            self.velocity.1 += change.1 * scale;
+        }
+
+        let x = self.velocity.0 * dt + self.remainder.0;
+        let y = self.velocity.1 * dt + self.remainder.1;
+        let (whole_x, whole_y) = (x.trunc(), y.trunc());
+        self.remainder = (x - whole_x, y - whole_y);
+
+        (whole_x as Coord, whole_y as Coord)
+    }
+
+    // the pointer stands still and the input is inside the dead zone
+    #[inline]
+    fn is_idle(&self) -> bool {
+        self.velocity == (0.0, 0.0) && self.target_velocity() == (0.0, 0.0)
+    }
+
+    // ticks every `interval` (e.g. `VirtualDevice::writing_interval`) on the flush thread of the device,
+    // sleeps until the input changes while the pointer stands still. stops when the device is gone.
+    // fails if the device has no pointer
+    pub fn spawn(mut self, interval: Duration, device: DeviceHandle) -> Result<StopHandle> {
+        device.capabilities().ensure_motion()?;
+        let wakeup = self.input.wakeup.clone();
+        let mut last_tick = None;
+
+        Ok(device.spawn_service(&wakeup, move |now, events| {
+            let (x, y) = self.tick(last_tick.map_or(Duration::ZERO, |last_tick| now - last_tick));
+
+            if x != 0 || y != 0 {
+                events.push(ChannelEvent::Now(Frame::builder().move_by(x as f64, y as f64).build()));
+            }
+            last_tick = (!self.is_idle()).then_some(now);
+            ControlFlow::Continue(last_tick.map(|last_tick| last_tick + interval))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a quarter of a second at full deflection moves by 100 pixels
+    const TICK: Duration = Duration::from_millis(250);
+
+    fn controller(dead_zone: f64, curve: ResponseCurve, acceleration: f64) -> PointerController {
+        PointerController::new(PointerSettings { dead_zone, curve, max_speed: 400.0, acceleration })
+    }
+
+    #[test]
+    fn dead_zone_is_ignored_and_the_rest_is_rescaled() {
+        let mut pointer = controller(0.5, ResponseCurve::Linear, 0.0);
+
+        pointer.set_input(0.3, -0.3);
+        assert_eq!(pointer.tick(TICK), (0, 0));
+        assert!(pointer.is_idle());
+
+        pointer.set_input(0.75, 0.0);
+        assert_eq!(pointer.tick(TICK), (50, 0));
+        pointer.set_input(0.0, -1.0);
+        assert_eq!(pointer.tick(TICK), (0, -100));
+    }
+
+    #[test]
+    fn response_curves_shape_the_speed() {
+        let half_speed = |curve| {
+            let mut pointer = controller(0.5, curve, 0.0);
+            pointer.set_input(0.75, 0.0);
+            pointer.tick(TICK).0
+        };
+
+        assert_eq!(half_speed(ResponseCurve::Linear), 50);
+        assert_eq!(half_speed(ResponseCurve::Quadratic), 25);
+        assert_eq!(half_speed(ResponseCurve::Custom(f64::sqrt)), 70);
+        // custom curves can't go past full speed
+        assert_eq!(half_speed(ResponseCurve::Custom(|_| 2.0)), 100);
+    }
+
+    #[test]
+    fn speed_is_clamped_to_max_speed() {
+        let mut pointer = controller(0.0, ResponseCurve::Linear, 0.0);
+
+        pointer.set_input(5.0, 0.0);
+        assert_eq!(pointer.input().get(), (1.0, 0.0));
+        assert_eq!(pointer.tick(TICK), (100, 0));
+
+        // a corner of the stick is as fast as full deflection along one axis
+        pointer.set_input(1.0, 1.0);
+        assert_eq!(pointer.tick(TICK), (70, 70));
+    }
+
+    #[test]
+    fn acceleration_limits_the_change_between_ticks() {
+        let mut pointer = controller(0.0, ResponseCurve::Linear, 800.0);
+
+        pointer.set_input(1.0, 0.0);
+        assert_eq!(pointer.tick(TICK), (50, 0));
+        assert_eq!(pointer.tick(TICK), (100, 0));
+        assert_eq!(pointer.tick(TICK), (100, 0));
+
+        pointer.set_input(0.0, 0.0);
+        assert_eq!(pointer.tick(TICK), (50, 0));
+        assert!(!pointer.is_idle());
+        assert_eq!(pointer.tick(TICK), (0, 0));
+        assert!(pointer.is_idle());
+    }
+
+    #[test]
+    fn sub_pixel_motion_is_carried_across_ticks() {
+        let mut pointer = PointerController::new(PointerSettings {
+            dead_zone: 0.0,
+            curve: ResponseCurve::Linear,
+            max_speed: 10.0,
+            acceleration: 0.0,
+        });
+
+        pointer.set_input(1.0, 0.0);
+        let moves: Vec<Coord> = (0..4).map(|_| pointer.tick(TICK).0).collect();
+        assert_eq!(moves, [2, 3, 2, 3]);
+
+        pointer.set_input(-1.0, 0.0);
+        let moves: Vec<Coord> = (0..4).map(|_| pointer.tick(TICK).0).collect();
+        assert_eq!(moves, [-2, -3, -2, -3]);
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::thread::{JoinHandle, sleep};
+use std::time::{Duration, Instant};
+
+use crate::{Backpressure, ChannelEvent, ChannelSender, EmptyResult};
+
+// calls `tick` every `interval` on a new thread until it breaks,
+// the time spent in `tick` is subtracted from the sleep
+pub(crate) fn spawn_interval_loop<F>(interval: Duration, mut tick: F) -> JoinHandle<()>
+where
+    F: FnMut() -> ControlFlow<()> + Send + 'static,
+{
+    thread::spawn(move || {
+        loop {
+            let start = Instant::now();
+
+            if tick().is_break() {
+                break;
+            }
+
+            let runtime = start.elapsed();
+
+            if let Some(remaining) = interval.checked_sub(runtime) {
+                sleep(remaining);
+            }
+        }
+    })
+}
+
+// the flush thread calls it with the current time, it pushes what to send and returns when it wants
+// to run next. `None` waits for `Wakeup::notify`, breaking removes the service
+pub(crate) type ServiceTick = dyn FnMut(Instant, &mut Vec<ChannelEvent>) -> ControlFlow<(), Option<Instant>> + Send;
+
+struct ServiceState {
+    // `None` once the service is stopped or ended
+    tick: Mutex<Option<Box<ServiceTick>>>,
+    // set by the flush thread before each tick, the first notification after it wakes the service up
+    idle: AtomicBool,
+    sender: ChannelSender,
+}
+
+// background work that runs on the flush thread of a device, registered and woken up
+// by sending it through the channel
+#[derive(Clone)]
+pub struct Service(Arc<ServiceState>);
+
+impl Service {
+    // runs right away once the flush thread receives it, `wakeup` is connected before that
+    // so no notification gets lost
+    pub(crate) fn spawn<F>(sender: &ChannelSender, wakeup: &Wakeup, tick: F) -> StopHandle
+    where
+        F: FnMut(Instant, &mut Vec<ChannelEvent>) -> ControlFlow<(), Option<Instant>> + Send + 'static,
+    {
+        let service = Service(Arc::new(ServiceState {
+            tick: Mutex::new(Some(Box::new(tick))),
+            idle: AtomicBool::new(false),
+            // wake-ups never wait for space, the flush thread may be the one sending them
+            sender: sender.with_backpressure(Backpressure::Unbounded),
+        }));
+        let _ = wakeup.0.set(service.clone());
+        if service.send().is_err() {
+            service.end();
+        }
+        StopHandle { service: Some(service) }
+    }
+
+    #[inline]
+    fn send(&self) -> EmptyResult {
+        self.0.sender.send(ChannelEvent::Service(self.clone()))
+    }
+
+    fn wake(&self) {
+        if self.0.idle.swap(false, AtomicOrdering::AcqRel) {
+            let _ = self.send();
+        }
+    }
+
+    // called by the flush thread, `Break` once the service is stopped or ended
+    pub(crate) fn run(&self, now: Instant, events: &mut Vec<ChannelEvent>) -> ControlFlow<(), Option<Instant>> {
+        let mut tick = self.0.tick.lock().unwrap();
+        let Some(service_tick) = tick.as_mut() else {
+            return ControlFlow::Break(());
+        };
+
+        self.0.idle.store(true, AtomicOrdering::Release);
+        let flow = service_tick(now, events);
+        if flow.is_break() {
+            *tick = None;
+        }
+        flow
+    }
+
+    // waits for a running tick to finish
+    pub(crate) fn end(&self) {
+        *self.0.tick.lock().unwrap() = None;
+    }
+
+    #[inline]
+    fn is_running(&self) -> bool {
+        self.0.tick.lock().unwrap().is_some()
+    }
+}
+
+impl fmt::Debug for Service {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Service").field("running", &self.is_running()).finish()
+    }
+}
+
+// the same registration
+impl PartialEq for Service {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// wakes up the service of an input when the input changes, does nothing until the service is spawned
+#[derive(Debug, Default)]
+pub(crate) struct Wakeup(OnceLock<Service>);
+
+impl Wakeup {
+    #[inline]
+    pub(crate) fn notify(&self) {
+        if let Some(service) = self.0.get() {
+            service.wake();
+        }
+    }
+}
+
+// handle of a background service, the service stops when the handle is dropped
+#[derive(Debug)]
+pub struct StopHandle {
+    service: Option<Service>,
+}
+
+impl StopHandle {
+    // false once stopped or once the service ended by itself, e.g. when the device is gone
+    pub fn is_running(&self) -> bool {
+        self.service.as_ref().is_some_and(Service::is_running)
+    }
+
+    // waits for the current tick to finish
+    pub fn stop(self) {
+        drop(self)
+    }
+
+    // keeps the service running for as long as the device exists
+    pub fn detach(mut self) {
+        self.service = None;
+    }
+}
+
+impl Drop for StopHandle {
+    fn drop(&mut self) {
+        if let Some(service) = self.service.take() {
+            service.end();
+            // lets the flush thread forget the service right away instead of at its next deadline
+            let _ = service.send();
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use crate::utils::Rng;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Jitter {
+    None,
+    // uniformly distributed in [-max, +max]
+    Uniform(Duration),
+    // normally distributed with the given standard deviation
+    Normal(Duration),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimingProfile {
+    pub hold: Duration,
+    pub inter_key: Duration,
+    pub word_pause: Duration,
+    // gap between the release and the next press of double and triple clicks
+    pub multi_click_interval: Duration,
+    pub jitter: Jitter,
+    pub seed: u64,
+}
+
+impl Default for TimingProfile {
+    fn default() -> Self {
+        Self {
+            hold: Duration::from_millis(5),
+            inter_key: Duration::ZERO,
+            word_pause: Duration::ZERO,
+            multi_click_interval: Duration::from_millis(80),
+            jitter: Jitter::None,
+            seed: 0,
+        }
+    }
+}
+
+impl TimingProfile {
+    pub fn fixed(hold: Duration, inter_key: Duration) -> Self {
+        Self {
+            hold,
+            inter_key,
+            ..Self::default()
+        }
+    }
+
+    pub fn human(seed: u64) -> Self {
+        Self {
+            hold: Duration::from_millis(70),
+            inter_key: Duration::from_millis(110),
+            word_pause: Duration::from_millis(250),
+            multi_click_interval: Duration::from_millis(120),
+            jitter: Jitter::Normal(Duration::from_millis(25)),
+            seed,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Timing {
+    profile: TimingProfile,
+    rng: Rng,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self::new(TimingProfile::default())
+    }
+}
+
+impl From<TimingProfile> for Timing {
+    fn from(profile: TimingProfile) -> Self {
+        Self::new(profile)
+    }
+}
+
+impl Timing {
+    pub fn new(profile: TimingProfile) -> Self {
+        let rng = Rng::new(profile.seed);
+        Self { profile, rng }
+    }
+
+    #[inline]
+    pub fn profile(&self) -> &TimingProfile {
+        &self.profile
+    }
+
+    #[inline]
+    pub fn hold(&mut self) -> Duration {
+        self.jittered(self.profile.hold)
+    }
+
+    #[inline]
+    pub fn inter_key(&mut self) -> Duration {
+        self.jittered(self.profile.inter_key)
+    }
+
+    #[inline]
+    pub fn word_pause(&mut self) -> Duration {
+        self.jittered(self.profile.word_pause)
+    }
+
+    #[inline]
+    pub fn multi_click_interval(&mut self) -> Duration {
+        self.jittered(self.profile.multi_click_interval)
+    }
+
+    fn jittered(&mut self, base: Duration) -> Duration {
+        if base.is_zero() {
+            return base;
+        }
+
+        let offset = match self.profile.jitter {
+            Jitter::None => return base,
+            Jitter::Uniform(max) => self.rng.uniform(-1.0, 1.0) * max.as_secs_f64(),
+            Jitter::Normal(std_dev) => self.rng.normal() * std_dev.as_secs_f64(),
+        };
+
+        Duration::from_secs_f64((base.as_secs_f64() + offset).max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(jitter: Jitter) -> TimingProfile {
+        TimingProfile { hold: Duration::from_millis(50), jitter, seed: 7, ..TimingProfile::default() }
+    }
+
+    #[test]
+    fn uniform_jitter_stays_within_its_bounds() {
+        let mut timing = Timing::new(profile(Jitter::Uniform(Duration::from_millis(10))));
+        let holds: Vec<_> = (0..1000).map(|_| timing.hold()).collect();
+
+        assert!(holds.iter().all(|hold| (Duration::from_millis(40)..=Duration::from_millis(60)).contains(hold)));
+        assert!(holds.iter().any(|hold| *hold != holds[0]));
+    }
+
+    #[test]
+    fn normal_jitter_never_goes_below_zero() {
+        let mut timing = Timing::new(profile(Jitter::Normal(Duration::from_millis(100))));
+        let holds: Vec<_> = (0..1000).map(|_| timing.hold()).collect();
+
+        assert!(holds.contains(&Duration::ZERO));
+        assert!(holds.iter().any(|hold| *hold > Duration::from_millis(150)));
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_timing() {
+        let jitter = Jitter::Normal(Duration::from_millis(25));
+        let (mut first, mut second) = (Timing::new(profile(jitter)), Timing::new(profile(jitter)));
+
+        for _ in 0..100 {
+            assert_eq!(first.hold(), second.hold());
+        }
+    }
+
+    #[test]
+    fn zero_durations_are_not_jittered() {
+        let mut timing = Timing::new(profile(Jitter::Uniform(Duration::from_millis(10))));
+        assert_eq!(timing.inter_key(), Duration::ZERO);
+    }
+}
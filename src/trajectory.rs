@@ -0,0 +1,216 @@
+use std::time::Duration;
+
+use crate::utils::Rng;
+use crate::{ChannelCommand, Coord, Frame, DEFAULT_MOTION_RATE};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HumanMotion {
+    pub seed: u64,
+    pub rate_hz: u32,
+    // Fitts' law: duration = fitts_a + fitts_b * log2(distance / target_width + 1)
+    pub fitts_a: Duration,
+    pub fitts_b: Duration,
+    pub target_width: f64,
+    // largest sideways offset of the Bezier control points, as a fraction of the distance
+    pub curvature: f64,
+    // how far past the target the pointer travels before correcting, as a fraction of the distance
+    pub overshoot: f64,
+    // standard deviation of the hand tremor in pixels
+    pub jitter: f64,
+}
+
+impl Default for HumanMotion {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            rate_hz: DEFAULT_MOTION_RATE,
+            fitts_a: Duration::from_millis(100),
+            fitts_b: Duration::from_millis(100),
+            target_width: 10.0,
+            curvature: 0.25,
+            overshoot: 0.04,
+            jitter: 0.35,
+        }
+    }
+}
+
+impl HumanMotion {
+    pub fn duration_for(&self, distance: f64) -> Duration {
+        let difficulty = (distance / self.target_width.max(1.0) + 1.0).log2();
+        self.fitts_a + self.fitts_b.mul_f64(difficulty)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+impl Point {
+    fn lerp(self, other: Point, t: f64) -> Point {
+        Point {
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+        }
+    }
+}
+
+// minimum-jerk velocity profile: slow start, fast middle, slow end
+#[inline]
+fn minimum_jerk(t: f64) -> f64 {
+    t * t * t * (10.0 - 15.0 * t + 6.0 * t * t)
+}
+
+fn cubic_bezier(p0: Point, p1: Point, p2: Point, p3: Point, t: f64) -> Point {
+    let inv = 1.0 - t;
+    let (a, b, c, d) = (inv * inv * inv, 3.0 * inv * inv * t, 3.0 * inv * t * t, t * t * t);
+    Point {
+        x: a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+        y: a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+    }
+}
+
+// relative mouse frames along a curved path, yields (offset from start, dx, dy)
+#[derive(Clone, Debug)]
+pub struct Trajectory {
+    // (offset from start, absolute x, absolute y)
+    points: Vec<(Duration, Coord, Coord)>,
+    index: usize,
+    emitted_x: Coord,
+    emitted_y: Coord,
+}
+
+impl Trajectory {
+    pub fn new(dx: Coord, dy: Coord, motion: &HumanMotion, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut points = Vec::new();
+
+        let end = Point { x: dx as f64, y: dy as f64 };
+        let distance = end.x.hypot(end.y);
+
+        if distance > 0.0 {
+            let frame_interval = Duration::from_secs(1) / motion.rate_hz.max(1);
+            let direction = Point { x: end.x / distance, y: end.y / distance };
+            let normal = Point { x: -direction.y, y: direction.x };
+
+            let overshoot = distance * motion.overshoot * rng.uniform(0.5, 1.0);
+            let overshoot_end = Point {
+                x: end.x + direction.x * overshoot,
+                y: end.y + direction.y * overshoot,
+            };
+
+            let start = Point { x: 0.0, y: 0.0 };
+            let mut control = |t: f64| {
+                let offset = rng.uniform(-motion.curvature, motion.curvature) * distance;
+                let on_line = start.lerp(overshoot_end, t);
+                Point { x: on_line.x + normal.x * offset, y: on_line.y + normal.y * offset }
+            };
+            let (control_1, control_2) = (control(1.0 / 3.0), control(2.0 / 3.0));
+
+            let mut elapsed = Duration::ZERO;
+            let mut push_phase = |rng: &mut Rng, duration: Duration, position: &dyn Fn(f64) -> Point| {
+                let frame_count = (duration.as_secs_f64() * motion.rate_hz as f64).ceil().max(1.0) as u32;
+                for index in 1..=frame_count {
+                    let mut point = position(minimum_jerk(index as f64 / frame_count as f64));
+                    if index < frame_count {
+                        point.x += rng.normal() * motion.jitter;
+                        point.y += rng.normal() * motion.jitter;
+                    }
+                    points.push((elapsed, point.x.round() as Coord, point.y.round() as Coord));
+                    elapsed += frame_interval;
+                }
+            };
+
+            push_phase(&mut rng, motion.duration_for(distance), &|t| {
+                cubic_bezier(start, control_1, control_2, overshoot_end, t)
+            });
+            if overshoot >= 1.0 {
+                push_phase(&mut rng, motion.duration_for(overshoot), &|t| overshoot_end.lerp(end, t));
+            }
+
+            // rounding of the last sample must not leave the pointer off target
+            if let Some(last) = points.last_mut() {
+                last.1 = dx;
+                last.2 = dy;
+            }
+        }
+
+        Self {
+            points,
+            index: 0,
+            emitted_x: 0,
+            emitted_y: 0,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.points.last().map(|point| point.0).unwrap_or_default()
+    }
+
+    // frames ready for the channel, one move per step in the device coordinate system
+    pub fn event_frames(self) -> impl Iterator<Item = (Duration, Frame)> {
+        self.map(|(offset, dx, dy)| (offset, ChannelCommand::Move(dx as f64, dy as f64).into()))
+    }
+}
+
+impl Iterator for Trajectory {
+    type Item = (Duration, Coord, Coord);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(&(offset, x, y)) = self.points.get(self.index) {
+            self.index += 1;
+
+            let (dx, dy) = (x - self.emitted_x, y - self.emitted_y);
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+
+            self.emitted_x = x;
+            self.emitted_y = y;
+            return Some((offset, dx, dy));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps(dx: Coord, dy: Coord, seed: u64) -> Vec<(Duration, Coord, Coord)> {
+        Trajectory::new(dx, dy, &HumanMotion::default(), seed).collect()
+    }
+
+    #[test]
+    fn same_seed_reproduces_the_trajectory() {
+        assert_eq!(steps(400, -250, 42), steps(400, -250, 42));
+        assert_ne!(steps(400, -250, 42), steps(400, -250, 43));
+    }
+
+    #[test]
+    fn trajectories_end_on_target() {
+        for seed in 0..20 {
+            for (dx, dy) in [(400, -250), (-3, 1), (0, 900), (1, 0)] {
+                let steps = steps(dx, dy, seed);
+                let sum = steps.iter().fold((0, 0), |(sx, sy), (_, x, y)| (sx + x, sy + y));
+                assert_eq!(sum, (dx, dy), "seed {seed}");
+                assert!(steps.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            }
+        }
+    }
+
+    #[test]
+    fn zero_distance_has_no_steps() {
+        let trajectory = Trajectory::new(0, 0, &HumanMotion::default(), 1);
+        assert_eq!(trajectory.duration(), Duration::ZERO);
+        assert_eq!(trajectory.count(), 0);
+    }
+
+    #[test]
+    fn duration_follows_fitts_law() {
+        let motion = HumanMotion::default();
+        assert_eq!(motion.duration_for(0.0), motion.fitts_a);
+        assert!(motion.duration_for(800.0) > motion.duration_for(100.0));
+    }
+}
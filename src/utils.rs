@@ -1,40 +1,144 @@
-use std::cmp::min;
 use crate::Coord;
 
-#[derive(PartialEq, Copy, Clone, Default, Debug)]
-pub struct GradualMove {
-    pub x_direction: Coord,
-    pub y_direction: Coord,
-    pub both_move: Coord,
-    pub move_only_x: Coord,
-    pub move_only_y: Coord,
+// DDA line rasteriser: splits (x, y) into steps that follow the true slope,
+// no step moves more than `max_step` along either axis
+#[derive(Clone, Debug)]
+pub struct LineSteps {
+    x: i64,
+    y: i64,
+    step_count: i64,
+    index: i64,
+    emitted_x: i64,
+    emitted_y: i64,
 }
 
-impl GradualMove {
-    pub fn calculate(x: Coord, y: Coord) -> Self {
-        // println!("Diff X: {}, Diff Y: {}", mouse_diff.x, mouse_diff.y);
+impl LineSteps {
+    pub fn new(x: Coord, y: Coord, max_step: Coord) -> Self {
+        let max_step = max_step.max(1) as i64;
+        let (x, y) = (x as i64, y as i64);
+        let longest = x.abs().max(y.abs());
 
-        let x_direction = x.signum();
-        let y_direction = y.signum();
+        Self {
+            x,
+            y,
+            step_count: (longest + max_step - 1) / max_step,
+            index: 0,
+            emitted_x: 0,
+            emitted_y: 0,
+        }
+    }
+}
 
-        let move_x = x.abs();
-        let move_y = y.abs();
+// rounds `value * index / count` to the nearest integer, halves away from zero
+#[inline]
+fn scaled_round(value: i64, index: i64, count: i64) -> i64 {
+    let numerator = value * index;
+    let half = count / 2;
+    if numerator >= 0 {
+        (numerator + half) / count
+    } else {
+        (numerator - half) / count
+    }
+}
 
-        let both_move = min(move_x, move_y);
+impl Iterator for LineSteps {
+    type Item = (Coord, Coord);
 
-        // println!("Dir X: {}, Dir Y: {}, Move both: {}", x_direction, y_direction, both_move);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.step_count {
+            return None;
+        }
+        self.index += 1;
 
-        let move_only_x = move_x - both_move;
-        let move_only_y = move_y - both_move;
+        let target_x = scaled_round(self.x, self.index, self.step_count);
+        let target_y = scaled_round(self.y, self.index, self.step_count);
 
-        // println!("Only X: {}, Only Y: {}\n", move_only_x, move_only_y);
+        let step = ((target_x - self.emitted_x) as Coord, (target_y - self.emitted_y) as Coord);
+        self.emitted_x = target_x;
+        self.emitted_y = target_y;
+        Some(step)
+    }
 
-        Self {
-            x_direction,
-            y_direction,
-            both_move,
-            move_only_x,
-            move_only_y,
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.step_count - self.index) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+// splitmix64, enough for reproducible jitter without pulling in `rand`
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn uniform(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+
+    // standard normal distribution, Box-Muller transform
+    pub fn normal(&mut self) -> f64 {
+        let u1 = 1.0 - self.next_f64();
+        let u2 = self.next_f64();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps(x: Coord, y: Coord, max_step: Coord) -> Vec<(Coord, Coord)> {
+        LineSteps::new(x, y, max_step).collect()
+    }
+
+    #[test]
+    fn steps_add_up_to_the_line() {
+        for (x, y, max_step) in [(10, 3, 1), (-7, 12, 2), (5, -5, 3), (100, -1, 7), (-3, -9, 1)] {
+            let steps = steps(x, y, max_step);
+            let sum = steps.iter().fold((0, 0), |(sx, sy), (dx, dy)| (sx + dx, sy + dy));
+            assert_eq!(sum, (x, y));
+            assert!(steps.iter().all(|(dx, dy)| dx.abs() <= max_step && dy.abs() <= max_step));
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn step_count_follows_the_longer_axis() {
+        assert_eq!(steps(10, 3, 1).len(), 10);
+        assert_eq!(steps(-3, 10, 3).len(), 4);
+        assert_eq!(LineSteps::new(7, 2, 2).size_hint(), (4, Some(4)));
+    }
+
+    #[test]
+    fn follows_the_slope() {
+        assert_eq!(steps(4, 2, 1), [(1, 1), (1, 0), (1, 1), (1, 0)]);
+        assert_eq!(steps(0, -3, 1), [(0, -1), (0, -1), (0, -1)]);
+    }
+
+    #[test]
+    fn empty_line_has_no_steps() {
+        assert!(steps(0, 0, 1).is_empty());
+    }
+
+    #[test]
+    fn non_positive_max_step_counts_as_one() {
+        assert_eq!(steps(3, 0, 0), steps(3, 0, 1));
+    }
+}
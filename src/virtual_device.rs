@@ -1,77 +1,285 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeSet, BinaryHeap};
+use std::ops::ControlFlow;
 use std::path::Path;
-use std::{fs, mem, ptr, slice, thread};
+use std::{fs, mem, slice};
 use std::ffi::CString;
 use std::fs::File;
 use std::io::Write;
 use std::os::fd::AsRawFd;
-use std::thread::{JoinHandle, sleep};
-use std::time::{Duration, Instant, SystemTime};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
 use nix::errno::Errno;
-use crossbeam_channel::{Sender, Receiver, bounded};
-use libc::gettimeofday;
+use crossbeam_channel::{Receiver, bounded, select};
 
 use crate::*;
-use crate::utils::GradualMove;
+use crate::channel::{ChannelReceiver, SentEvent, channel};
+use crate::coalesce::coalesce;
+use crate::scheduler::spawn_interval_loop;
+use crate::utils::{LineSteps, Rng};
+use crate::writer::{EventBuffer, write_whole};
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 pub type EmptyResult = Result<()>;
 
-pub type Button = u16;
+pub type Button = Key;
 pub type Coord = i32;
 
 pub type EventParams = (u16, u16, i32);
-pub type ChannelSender = Sender<EventParams>;
-type ChannelReceiver = Receiver<EventParams>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelCommand {
+    Event(EventParams),
+    // relative motion and scroll in the device coordinate system,
+    // the fractional part is carried over to later events
+    Move(f64, f64),
+    Scroll(f64, f64),
+}
+
+// returned by `DeviceHandle::send_at` and `send_after`, unique among all handles of a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScheduleId(u64);
+
+impl ScheduleId {
+    #[inline]
+    pub(crate) fn next(counter: &AtomicU64) -> Self {
+        ScheduleId(counter.fetch_add(1, AtomicOrdering::Relaxed))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelEvent {
+    Now(Frame),
+    At(Instant, Frame),
+    // same as `At` but can be cancelled until it is written
+    Scheduled(ScheduleId, Instant, Frame),
+    Cancel(ScheduleId),
+    // replaces the running fling, any other scroll cancels it
+    Fling(Fling),
+    // registers a service with the flush thread, or wakes it up if it is already registered
+    Service(Service),
+}
+
+impl From<Frame> for ChannelEvent {
+    fn from(frame: Frame) -> Self {
+        ChannelEvent::Now(frame)
+    }
+}
+
+impl From<EventParams> for ChannelEvent {
+    fn from(event: EventParams) -> Self {
+        ChannelEvent::Now(event.into())
+    }
+}
+
+impl From<InputEvent> for ChannelEvent {
+    fn from(event: InputEvent) -> Self {
+        ChannelEvent::Now(event.into())
+    }
+}
+
+struct ScheduledFrame {
+    deadline: Instant,
+    // keeps frames with the same deadline in sending order
+    sequence: u64,
+    id: Option<ScheduleId>,
+    frame: Frame,
+}
+
+impl PartialEq for ScheduledFrame {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScheduledFrame {}
+
+impl PartialOrd for ScheduledFrame {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScheduledFrame {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.deadline, self.sequence).cmp(&(other.deadline, other.sequence))
+    }
+}
+
+// reused by every flush of the flush thread, they only allocate while growing.
+// `batch` is also the scratch buffer of `emit` and `release_all`
+#[derive(Default)]
+struct FlushBuffers {
+    incoming: Vec<SentEvent>,
+    // events pushed by services
+    emitted: Vec<ChannelEvent>,
+    batch: Vec<EventParams>,
+    coalesced: Vec<EventParams>,
+}
+
+impl FlushBuffers {
+    fn clear(&mut self) {
+        self.incoming.clear();
+        self.emitted.clear();
+        self.batch.clear();
+        self.coalesced.clear();
+    }
+}
 
 pub struct VirtualDevice {
     writing_interval: Duration,
     file: File,
+    write_timeout: Duration,
+    buffer: EventBuffer,
     def: uinput_user_dev,
+    capabilities: Capabilities,
+    pressed: BTreeSet<Key>,
+    abs_position: (Coord, Coord),
+    screen_geometry: Option<ScreenGeometry>,
+    timing: Timing,
+    motion_rate: u32,
+    max_gradual_step: Coord,
+    human_motion: HumanMotion,
+    human_rng: Rng,
+    coordinate_system: CoordinateSystem,
+    rel_remainders: [f64; REL_CNT as usize],
+    coalescing: bool,
+    fling: Option<(Fling, Instant)>,
+    // frames scheduled from this sequence on were sent after the fling started, their scrolls cancel it
+    fling_sequence: u64,
+    scheduled: BinaryHeap<Reverse<ScheduledFrame>>,
+    scheduled_count: u64,
+    schedule_ids: Arc<AtomicU64>,
+    // services registered with the flush thread and when they want to run next
+    services: Vec<(Service, Option<Instant>)>,
     pub sender: ChannelSender,
     receiver: ChannelReceiver,
+    flush_buffers: FlushBuffers,
+}
+
+pub(crate) const FIXED_TIME: timeval = timeval { tv_sec: 0, tv_usec: 0 };
+pub(crate) const SYN_PARAMS: EventParams = (EV_SYN, SYN_REPORT, 0);
+// scroll values are in hi-res wheel units
+const HI_RES_PER_NOTCH: f64 = 120.0;
+pub(crate) const SCROLL_AXES: [RelAxis; 4] = [RelAxis::HWHEEL_HI_RES, RelAxis::WHEEL_HI_RES, RelAxis::HWHEEL_NOTCH, RelAxis::WHEEL_NOTCH];
+
+
+// adds `value` to the remainder of the axis and takes out the whole units
+#[inline]
+fn accumulate(remainders: &mut [f64; REL_CNT as usize], axis: RelAxis, value: f64) -> Coord {
+    match remainders.get_mut(axis.code() as usize) {
+        Some(remainder) => {
+            *remainder += value;
+            let whole = remainder.trunc();
+            *remainder -= whole;
+            whole as Coord
+        }
+        None => value.trunc() as Coord,
+    }
+}
+
+
+pub(crate) struct Keystroke {
+    // pause before the key goes down
+    pub(crate) delay: Duration,
+    pub(crate) key: Key,
+    pub(crate) shift: bool,
+    pub(crate) hold: Duration,
 }
 
-const FIXED_TIME: timeval = timeval { tv_sec: 0, tv_usec: 0 };
-const SYN_PARAMS: EventParams = (EV_SYN, SYN_REPORT, 0);
+pub(crate) fn plan_keystrokes(text: &str, timing: &mut Timing) -> Result<Vec<Keystroke>> {
+    let keys = text_to_keys(text)?;
+    let mut keystrokes = Vec::with_capacity(keys.len());
+    let mut delay = Duration::ZERO;
+
+    for (c, (key, shift)) in text.chars().zip(keys) {
+        keystrokes.push(Keystroke { delay, key, shift, hold: timing.hold() });
+
+        delay = timing.inter_key();
+        if c.is_whitespace() {
+            delay += timing.word_pause();
+        }
+    }
+    Ok(keystrokes)
+}
 
-const SLEEP_BEFORE_RELEASE: Duration = Duration::from_millis(5);
+// channel sequences are (offset from the start, frame), see `ChannelSender::send_sequence`
+pub(crate) fn plan_click(button: Button, hold: Duration) -> Vec<(Duration, Frame)> {
+    vec![
+        (Duration::ZERO, Frame::builder().press(button).build()),
+        (hold, Frame::builder().release(button).build()),
+    ]
+}
 
+pub(crate) fn plan_multi_click(button: Button, count: usize, timing: &mut Timing) -> Vec<(Duration, Frame)> {
+    let mut frames = Vec::with_capacity(count * 2);
+    let mut offset = Duration::ZERO;
 
-#[inline(always)]
-fn convert_event_for_writing(kind: u16, code: u16, value: i32, input_event: &mut input_event) -> Vec<u8> {
-    input_event.time = FIXED_TIME;
-    input_event.kind = kind;
-    input_event.code = code;
-    input_event.value = value;
+    for index in 0..count {
+        if index > 0 {
+            offset += timing.multi_click_interval();
+        }
+        frames.push((offset, Frame::builder().press(button).build()));
+        offset += timing.hold();
+        frames.push((offset, Frame::builder().release(button).build()));
+    }
+    frames
+}
 
-    // let mut input_event = input_event {
-    //     time: FIXED_TIME,
-    //     kind,
-    //     code,
-    //     value,
-    // };
+pub(crate) fn plan_type_text(text: &str, timing: &mut Timing) -> Result<Vec<(Duration, Frame)>> {
+    let keystrokes = plan_keystrokes(text, timing)?;
+    let mut frames = Vec::with_capacity(keystrokes.len() * 2);
+    let mut offset = Duration::ZERO;
 
-    unsafe {
-        // gettimeofday(&mut input_event.time, ptr::null_mut());
+    for keystroke in keystrokes {
+        let (mut press, mut release) = (Frame::builder(), Frame::builder());
+        if keystroke.shift {
+            press = press.press(KEY_LEFTSHIFT);
+        }
+        press = press.press(keystroke.key);
+        release = release.release(keystroke.key);
+        if keystroke.shift {
+            release = release.release(KEY_LEFTSHIFT);
+        }
 
-        let ptr = input_event as *const _ as *const u8;
-        let size = mem::size_of_val(input_event);
-        let content = slice::from_raw_parts(ptr, size);
-        content.to_vec()
+        offset += keystroke.delay;
+        frames.push((offset, press.build()));
+        offset += keystroke.hold;
+        frames.push((offset, release.build()));
     }
+    Ok(frames)
+}
+
+pub(crate) fn plan_motion(frames: impl IntoIterator<Item = (Duration, Coord, Coord)>) -> Vec<(Duration, Frame)> {
+    frames.into_iter()
+        .map(|(offset, dx, dy)| (offset, Frame::builder().move_by(dx as f64, dy as f64).build()))
+        .collect()
 }
 
 pub enum DeviceDefinitionType{
     Separate,
     MouseOnly,
     KeyboardOnly,
+    AbsoluteMouse { max_x: Coord, max_y: Coord },
     None,
 }
 
+// what a single device registers, `DeviceDefinitionType` without `Separate`
+enum Registration {
+    Mouse,
+    Keyboard,
+    AbsoluteMouse { max_x: Coord, max_y: Coord },
+    MouseAndKeyboard,
+}
+
 const UINPUT_NOT_LOADED_ERR: &str = "'uinput' module probably is not loaded. try: 'sudo modprobe uinput'";
 
 
 impl VirtualDevice {
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Result<Self> {
         Self::default_single_device(DeviceDefinitionType::None)
     }
@@ -84,6 +292,10 @@ impl VirtualDevice {
         )
     }
 
+    pub fn default_absolute(max_x: Coord, max_y: Coord) -> Result<Self> {
+        Self::default_single_device(DeviceDefinitionType::AbsoluteMouse { max_x, max_y })
+    }
+
     pub fn default_separate() -> Result<(Self, Self)> {
         Ok((
             Self::default_single_device(DeviceDefinitionType::MouseOnly)?,
@@ -91,9 +303,40 @@ impl VirtualDevice {
         ))
     }
 
-    fn new(writing_interval: Duration, channel_size: usize, definition_type: DeviceDefinitionType) -> Result<Self> {
-        let (s, r) = bounded(channel_size);
-
+    // `channel_size` is how many events may wait for the flush thread before `Backpressure` kicks in
+    // `Separate` needs two devices, it is rejected here and created by `default_separate`
+    pub fn new(writing_interval: Duration, channel_size: usize, definition_type: DeviceDefinitionType) -> Result<Self> {
+        // Mouse:
+        // Bus=0003 Vendor=045e Product=07a5 Version=0111
+        // Keyboard:
+        // Bus=0011 Vendor=0001 Product=0001 Version=ab83
+        // Absolute mouse (QEMU USB tablet):
+        // Bus=0003 Vendor=0627 Product=0001 Version=0001
+        let (id, device_name, registration) = match definition_type {
+            DeviceDefinitionType::Separate => {
+                return Err(Box::from("A single device can't be `Separate`, use `VirtualDevice::default_separate`"));
+            }
+            DeviceDefinitionType::MouseOnly => (
+                input_id { bustype: 0x0003, vendor: 0x045e, product: 0x07a5, version: 0x0111 },
+                "virtual-mouse",
+                Registration::Mouse,
+            ),
+            DeviceDefinitionType::KeyboardOnly => (
+                input_id { bustype: 0x0011, vendor: 0x0001, product: 0x0001, version: 0xab83 },
+                "virtual-keyboard",
+                Registration::Keyboard,
+            ),
+            DeviceDefinitionType::AbsoluteMouse { max_x, max_y } => (
+                input_id { bustype: 0x0003, vendor: 0x0627, product: 0x0001, version: 0x0001 },
+                "virtual-absolute-mouse",
+                Registration::AbsoluteMouse { max_x, max_y },
+            ),
+            DeviceDefinitionType::None => (
+                input_id { bustype: 0, vendor: 0, product: 0, version: 0 },
+                "virtual-device",
+                Registration::MouseAndKeyboard,
+            ),
+        };
         let path = Path::new("/dev/uinput");
 
         #[cfg(feature = "auto-acquire-permissions")]
@@ -113,66 +356,28 @@ impl VirtualDevice {
             // .custom_flags(libc::O_WRONLY | libc::O_NDELAY)
             .open(path)?;
 
-        // Mouse:
-        // Bus=0003 Vendor=045e Product=07a5 Version=0111
-        // Keyboard:
-        // Bus=0011 Vendor=0001 Product=0001 Version=ab83
-
         let mut def: uinput_user_dev = unsafe { mem::zeroed() };
-        let mut device_name: String;
+        def.id = id;
 
-        match definition_type {
-            DeviceDefinitionType::Separate => {
-                return Err(Box::from("Not implemented"));
-            }
-            DeviceDefinitionType::MouseOnly => {
-                def.id = input_id {
-                    bustype: 0x0003,
-                    vendor: 0x045e,
-                    product: 0x07a5,
-                    version: 0x0111,
-                };
-                device_name = String::from("virtual-mouse");
-            }
-            DeviceDefinitionType::KeyboardOnly => {
-                def.id = input_id {
-                    bustype: 0x0011,
-                    vendor: 0x0001,
-                    product: 0x0001,
-                    version: 0xab83,
-                };
-                device_name = String::from("virtual-keyboard");
-            }
-            DeviceDefinitionType::None => {
-                device_name = String::from("virtual-device");
-            }
-        }
-
-        let mut virtual_device = VirtualDevice {
-            writing_interval,
-            file,
-            def,
-            sender: s,
-            receiver: r,
-        };
+        let mut virtual_device = VirtualDevice::from_parts(file, def, writing_interval, channel_size);
 
         // let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
         // let device_name = format!("virtualdevice-{}", now.as_millis());
         // println!("{}", device_name);
 
-        virtual_device.set_name(device_name.as_str())?;
+        virtual_device.set_name(device_name)?;
 
-        match definition_type {
-            DeviceDefinitionType::Separate => {
-                return Err(Box::from("Not implemented"));
-            }
-            DeviceDefinitionType::MouseOnly => {
+        match registration {
+            Registration::Mouse => {
                 virtual_device.register_mouse()?;
             }
-            DeviceDefinitionType::KeyboardOnly => {
+            Registration::Keyboard => {
                 virtual_device.register_keyboard()?;
             }
-            DeviceDefinitionType::None => {
+            Registration::AbsoluteMouse { max_x, max_y } => {
+                virtual_device.register_absolute_mouse(max_x, max_y)?;
+            }
+            Registration::MouseAndKeyboard => {
                 virtual_device.register_mouse()?;
                 virtual_device.register_keyboard()?;
             }
@@ -183,6 +388,40 @@ impl VirtualDevice {
         Ok(virtual_device)
     }
 
+    // the device state around an already opened fd, `new` registers and creates the device afterwards
+    pub(crate) fn from_parts(file: File, def: uinput_user_dev, writing_interval: Duration, channel_size: usize) -> Self {
+        let (s, r) = channel(channel_size);
+
+        VirtualDevice {
+            writing_interval,
+            file,
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+            buffer: EventBuffer::default(),
+            def,
+            capabilities: Capabilities::default(),
+            pressed: BTreeSet::new(),
+            abs_position: (0, 0),
+            screen_geometry: None,
+            timing: Timing::default(),
+            motion_rate: DEFAULT_MOTION_RATE,
+            max_gradual_step: 1,
+            human_motion: HumanMotion::default(),
+            human_rng: Rng::new(0),
+            coordinate_system: CoordinateSystem::default(),
+            rel_remainders: [0.0; REL_CNT as usize],
+            coalescing: false,
+            fling: None,
+            fling_sequence: 0,
+            scheduled: BinaryHeap::new(),
+            scheduled_count: 0,
+            schedule_ids: Arc::new(AtomicU64::new(0)),
+            services: Vec::new(),
+            sender: s,
+            receiver: r,
+            flush_buffers: FlushBuffers::default(),
+        }
+    }
+
     fn set_name<T: AsRef<str>>(&mut self, value: T) -> EmptyResult {
         let string = CString::new(value.as_ref())?;
         let bytes = string.as_bytes_with_nul();
@@ -196,7 +435,7 @@ impl VirtualDevice {
         }
 
         (&mut self.def.name)[..bytes.len()]
-            .clone_from_slice(unsafe { mem::transmute(bytes) });
+            .clone_from_slice(unsafe { mem::transmute::<&[u8], &[i8]>(bytes) });
 
         Ok(())
     }
@@ -214,518 +453,2225 @@ impl VirtualDevice {
         Ok(())
     }
 
-    fn register_keyboard(&self) -> EmptyResult {
+    fn register_keyboard(&mut self) -> EmptyResult {
         unsafe {
             Errno::result(ui_set_evbit(self.file.as_raw_fd(), EV_KEY as i32))?;
         }
         for code in 1..255 {
-            self.register_key(code)?
+            self.register_key(Key(code))?
+        }
+        Ok(())
+    }
+
+    fn register_mouse(&mut self) -> EmptyResult {
+        unsafe {
+            Errno::result(ui_set_evbit(self.file.as_raw_fd(), EV_KEY as i32))?;
+            Errno::result(ui_set_evbit(self.file.as_raw_fd(), EV_REL as i32))?;
         }
+        for key in [Key::BTN_LEFT, Key::BTN_RIGHT, Key::BTN_MIDDLE] {
+            self.register_key(key)?
+        }
+
+        for axis in [RelAxis::X, RelAxis::Y].into_iter().chain(SCROLL_AXES) {
+            self.register_relative(axis)?
+        }
+
         Ok(())
     }
 
-    fn register_mouse(&self) -> EmptyResult {
+    fn register_absolute_mouse(&mut self, max_x: Coord, max_y: Coord) -> EmptyResult {
         unsafe {
             Errno::result(ui_set_evbit(self.file.as_raw_fd(), EV_KEY as i32))?;
             Errno::result(ui_set_evbit(self.file.as_raw_fd(), EV_REL as i32))?;
+            Errno::result(ui_set_evbit(self.file.as_raw_fd(), EV_ABS as i32))?;
         }
-        for code in [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE] {
-            self.register_key(code)?
+        for key in [Key::BTN_LEFT, Key::BTN_RIGHT, Key::BTN_MIDDLE] {
+            self.register_key(key)?
         }
 
-        for code in [REL_X, REL_Y, REL_HWHEEL, REL_WHEEL] {
-            self.register_relative(code)?
+        for axis in SCROLL_AXES {
+            self.register_relative(axis)?
         }
 
+        self.register_absolute(AbsAxis::X, 0, max_x)?;
+        self.register_absolute(AbsAxis::Y, 0, max_y)?;
+
         Ok(())
     }
 
-    fn register_key(&self, code: u16) -> EmptyResult {
+    fn register_key(&mut self, key: Key) -> EmptyResult {
         unsafe {
             // Errno::result(ui_set_evbit(self.file.as_raw_fd(), EV_KEY as i32))?;
 
-            Errno::result(ui_set_keybit(self.file.as_raw_fd(), code as i32))?;
+            Errno::result(ui_set_keybit(self.file.as_raw_fd(), key.code() as i32))?;
         }
+        self.capabilities.add_key(key);
         Ok(())
     }
 
-    fn register_relative(&self, code: u16) -> EmptyResult {
+    fn register_relative(&mut self, axis: RelAxis) -> EmptyResult {
         unsafe {
             // Errno::result(ui_set_evbit(self.file.as_raw_fd(), EV_REL as i32))?;
 
-            Errno::result(ui_set_relbit(self.file.as_raw_fd(), code as i32))?;
+            Errno::result(ui_set_relbit(self.file.as_raw_fd(), axis.code() as i32))?;
         }
+        self.capabilities.add_rel(axis);
         Ok(())
     }
 
-    #[inline]
-    pub fn send_to_channel(kind: u16, code: u16, value: i32, sender: &ChannelSender) -> EmptyResult {
-        sender.send((kind, code, value))?;
+    fn register_absolute(&mut self, axis: AbsAxis, min: Coord, max: Coord) -> EmptyResult {
+        unsafe {
+            Errno::result(ui_set_absbit(self.file.as_raw_fd(), axis.code() as i32))?;
+        }
+        self.def.absmin[axis.code() as usize] = min;
+        self.def.absmax[axis.code() as usize] = max;
+        self.capabilities.add_abs(axis);
         Ok(())
     }
 
     #[inline]
-    pub fn send_press(button: Button, sender: &ChannelSender) -> EmptyResult {
-        sender.send((EV_KEY, button, 1))?;
-        sender.send(SYN_PARAMS)?;
-        Ok(())
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
     }
 
     #[inline]
-    pub fn send_release(button: Button, sender: &ChannelSender) -> EmptyResult {
-        sender.send((EV_KEY, button, 0))?;
-        Ok(())
+    pub(crate) fn ensure_key(&self, key: Key) -> EmptyResult {
+        self.capabilities.ensure_key(key)
     }
 
-    pub fn send_click(button: Button, sender: &ChannelSender) -> EmptyResult {
-        VirtualDevice::send_press(button, sender)?;
-        VirtualDevice::send_release(button, sender)
+    #[inline]
+    pub(crate) fn ensure_abs(&self, axis: AbsAxis) -> EmptyResult {
+        self.capabilities.ensure_abs(axis)
     }
 
     #[inline]
-    pub fn send_mouse_move_x(x: Coord, sender: &ChannelSender) -> EmptyResult {
-        sender.send((EV_REL, REL_X, x))?;
-        Ok(())
+    pub(crate) fn ensure_rel(&self, axis: RelAxis) -> EmptyResult {
+        self.capabilities.ensure_rel(axis)
     }
 
     #[inline]
-    pub fn send_mouse_move_y(y: Coord, sender: &ChannelSender) -> EmptyResult {
-        sender.send((EV_REL, REL_Y, -y))?;
-        Ok(())
+    pub(crate) fn ensure_supported(&self, event: &InputEvent) -> EmptyResult {
+        self.capabilities.ensure_supported(event)
     }
 
+    // fails once the flush thread has stopped and the device is gone
     #[inline]
-    pub fn send_mouse_move(x: Coord, y: Coord, sender: &ChannelSender) -> EmptyResult {
-        sender.send((EV_REL, REL_X, x))?;
-        sender.send((EV_REL, REL_Y, -y))?;
-        Ok(())
+    pub fn send_channel_event(event: ChannelEvent, sender: &ChannelSender) -> EmptyResult {
+        sender.send(event)
     }
 
     #[inline]
-    pub fn send_scroll_x(value: Coord, sender: &ChannelSender) -> EmptyResult {
-        sender.send((EV_REL, REL_HWHEEL, value))?;
-        Ok(())
+    pub fn send_to_channel(kind: u16, code: u16, value: i32, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_frame(Frame::from((kind, code, value)), sender)
     }
 
+    // every frame is written in one piece and followed by a single SYN_REPORT
     #[inline]
-    pub fn send_scroll_y(value: Coord, sender: &ChannelSender) -> EmptyResult {
-        sender.send((EV_REL, REL_WHEEL, value))?;
-        Ok(())
+    pub fn send_frame(frame: Frame, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_channel_event(ChannelEvent::Now(frame), sender)
     }
 
-    pub fn flush_channel_every_interval(mut self) -> JoinHandle<()> {
-        let writing_interval = self.writing_interval;
-
-        thread::spawn(move || {
-            loop {
-                let start = Instant::now();
-
-                self.write_events_from_channel().unwrap();
-
-                let runtime = start.elapsed();
-
-                if let Some(remaining) = writing_interval.checked_sub(runtime) {
-                    sleep(remaining);
-                }
-            }
-        })
-
-        // scheduler.join().expect("Scheduler panicked");
+    #[inline]
+    pub fn send_event(event: InputEvent, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_channel_event(event.into(), sender)
     }
 
     #[inline]
-    fn write_events_from_channel(&mut self) -> EmptyResult {
-        let mut converted = Vec::new();
-        self.sender.send(SYN_PARAMS)?;
-
-        for event in self.receiver.try_iter() {
-            // let mut content = convert_event_for_writing(event.0, event.1, event.2);
-            // converted.append(&mut content);
-
-            let mut input_event = input_event {
-                time: FIXED_TIME,
-                kind: event.0,
-                code: event.1,
-                value: event.2,
-            };
-
-            unsafe {
-                // gettimeofday(&mut input_event.time, ptr::null_mut());
-
-                let ptr = &input_event as *const _ as *const u8;
-                let size = mem::size_of_val(&input_event);
-                let content = slice::from_raw_parts(ptr, size);
-                converted.extend_from_slice(content);
-            }
-        }
-
-        self.file.write_all(converted.as_slice())?;
-        Ok(())
+    pub fn send_press(button: impl Into<Button>, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_frame(Frame::builder().press(button.into()).build(), sender)
     }
 
     #[inline]
-    pub fn write_batch(&mut self, batch: &[EventParams]) -> EmptyResult{
-        let mut converted = Vec::new();
-
-        for event in batch{
-            let mut input_event = input_event {
-                time: FIXED_TIME,
-                kind: event.0,
-                code: event.1,
-                value: event.2,
-            };
+    pub fn send_release(button: impl Into<Button>, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_frame(Frame::builder().release(button.into()).build(), sender)
+    }
 
-            unsafe {
-                // gettimeofday(&mut input_event.time, ptr::null_mut());
+    // holds for the timing profile of the device the sender belongs to
+    pub fn send_click(button: impl Into<Button>, sender: &ChannelSender) -> EmptyResult {
+        let hold = sender.timing().hold();
+        VirtualDevice::send_click_for(button, hold, sender)
+    }
 
-                let ptr = &input_event as *const _ as *const u8;
-                let size = mem::size_of_val(&input_event);
-                let content = slice::from_raw_parts(ptr, size);
-                converted.extend_from_slice(content);
-            }
-        }
-        self.file.write_all(converted.as_slice())?;
-        Ok(())
+    // the release is scheduled in the flush thread so the press lands in an earlier frame
+    pub fn send_click_for(button: impl Into<Button>, hold: Duration, sender: &ChannelSender) -> EmptyResult {
+        sender.send_sequence(plan_click(button.into(), hold))
     }
 
-    #[inline]
-    fn write(&mut self, kind: u16, code: u16, value: i32) -> EmptyResult {
-        // let content = convert_event_for_writing(kind, code, value);
-        // self.file.write_all(content.as_slice())?;
+    pub fn send_click_with(button: impl Into<Button>, timing: &mut Timing, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_click_for(button, timing.hold(), sender)
+    }
 
-        let mut input_event = input_event {
-            time: FIXED_TIME,
-            kind,
-            code,
-            value,
-        };
+    pub fn send_double_click(button: impl Into<Button>, sender: &ChannelSender) -> EmptyResult {
+        let frames = plan_multi_click(button.into(), 2, &mut sender.timing());
+        sender.send_sequence(frames)
+    }
 
-        unsafe {
-            // gettimeofday(&mut input_event.time, ptr::null_mut());
+    pub fn send_triple_click(button: impl Into<Button>, sender: &ChannelSender) -> EmptyResult {
+        let frames = plan_multi_click(button.into(), 3, &mut sender.timing());
+        sender.send_sequence(frames)
+    }
 
-            let ptr = &input_event as *const _ as *const u8;
-            let size = mem::size_of_val(&input_event);
-            let content = slice::from_raw_parts(ptr, size);
-            self.file.write_all(content)?;
-        }
+    pub fn send_multi_click_with(button: impl Into<Button>, count: usize, timing: &mut Timing, sender: &ChannelSender) -> EmptyResult {
+        sender.send_sequence(plan_multi_click(button.into(), count, timing))
+    }
 
-        Ok(())
+    pub fn send_type_text(text: &str, timing: &mut Timing, sender: &ChannelSender) -> EmptyResult {
+        sender.send_sequence(plan_type_text(text, timing)?)
     }
 
-    #[inline(always)]
-    pub fn synchronize(&mut self) -> EmptyResult {
-        self.write(EV_SYN, SYN_REPORT, 0)
+    #[inline]
+    pub fn send_mouse_move_x(x: Coord, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_mouse_move_f(x as f64, 0.0, sender)
     }
 
     #[inline]
-    pub fn move_mouse_raw_x(&mut self, x: Coord) -> EmptyResult {
-        self.write(EV_REL, REL_X, x)
+    pub fn send_mouse_move_y(y: Coord, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_mouse_move_f(0.0, y as f64, sender)
     }
 
     #[inline]
-    pub fn move_mouse_raw_y(&mut self, y: Coord) -> EmptyResult {
-        self.write(EV_REL, REL_Y, -y)
+    pub fn send_mouse_move(x: Coord, y: Coord, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_mouse_move_f(x as f64, y as f64, sender)
     }
 
     #[inline]
-    pub fn move_mouse_raw(&mut self, x: Coord, y: Coord) -> EmptyResult {
-        self.write_batch(&[
-            (EV_REL, REL_X, x),
-            (EV_REL, REL_Y, -y),
-        ])
+    pub fn send_mouse_move_f(x: f64, y: f64, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_frame(Frame::builder().move_by(x, y).build(), sender)
     }
 
     #[inline]
-    pub fn buffered_move_mouse_x(&mut self, x: Coord) -> Vec<EventParams> {
-        vec![
-            (EV_REL, REL_X, x),
-            SYN_PARAMS
-        ]
+    pub fn send_scroll_f(x: f64, y: f64, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_frame(Frame::builder().scroll(x, y).build(), sender)
     }
 
+    // velocities are in wheel units per second, `friction` is the exponential decay rate
     #[inline]
-    pub fn buffered_move_mouse_y(&mut self, y: Coord) -> Vec<EventParams> {
-        vec![
-            (EV_REL, REL_Y, -y),
-            SYN_PARAMS
-        ]
+    pub fn send_fling_scroll(velocity_x: f64, velocity_y: f64, friction: f64, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_fling_scroll_with(velocity_x, velocity_y, Decay::Exponential(friction), sender)
     }
 
     #[inline]
-    pub fn buffered_move_mouse(&mut self, x: Coord, y: Coord) -> Vec<EventParams> {
-        vec![
-            (EV_REL, REL_X, x),
-            (EV_REL, REL_Y, -y),
-            SYN_PARAMS
-        ]
+    pub fn send_fling_scroll_with(velocity_x: f64, velocity_y: f64, decay: Decay, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_channel_event(ChannelEvent::Fling(Fling::new(velocity_x, velocity_y, decay)?), sender)
     }
 
     #[inline]
-    pub fn gradual_move_mouse_raw(&mut self, x: Coord, y: Coord) -> Result<()> {
-        let gradual_move = GradualMove::calculate(x, y);
+    pub fn send_scroll_x(value: Coord, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_scroll_f(value as f64, 0.0, sender)
+    }
 
-        for _ in 0..gradual_move.both_move {
-            self.move_mouse_raw(gradual_move.x_direction, gradual_move.y_direction)?;
-        }
-        for _ in 0..gradual_move.move_only_x {
-            self.move_mouse_raw_x(gradual_move.x_direction)?;
-        }
-        for _ in 0..gradual_move.move_only_y {
-            self.move_mouse_raw_y(gradual_move.y_direction)?;
-        }
-        self.synchronize()?;
+    #[inline]
+    pub fn send_scroll_y(value: Coord, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_scroll_f(0.0, value as f64, sender)
+    }
 
-        Ok(())
+    pub fn send_mouse_move_over(x: Coord, y: Coord, duration: Duration, easing: Easing, rate_hz: u32, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_motion(MotionFrames::new(x, y, duration, easing, rate_hz), sender)
     }
 
-    #[inline]
-    pub fn gradual_move_mouse(&mut self, x: Coord, y: Coord) -> Result<()> {
-        let gradual_move = GradualMove::calculate(x, y);
+    pub fn send_mouse_move_human(x: Coord, y: Coord, motion: &HumanMotion, seed: u64, sender: &ChannelSender) -> EmptyResult {
+        VirtualDevice::send_motion(Trajectory::new(x, y, motion, seed), sender)
+    }
 
-        for _ in 0..gradual_move.both_move {
-            self.move_mouse(gradual_move.x_direction, gradual_move.y_direction)?;
-        }
-        for _ in 0..gradual_move.move_only_x {
-            self.move_mouse_x(gradual_move.x_direction)?;
-        }
-        for _ in 0..gradual_move.move_only_y {
-            self.move_mouse_y(gradual_move.y_direction)?;
-        }
+    // frames are (offset from the start, dx, dy), each one is written by the flush thread when it is due
+    pub fn send_motion(frames: impl IntoIterator<Item = (Duration, Coord, Coord)>, sender: &ChannelSender) -> EmptyResult {
+        sender.send_sequence(plan_motion(frames))
+    }
 
-        Ok(())
+    // the handle starts with the timing and motion settings the device has right now
+    // and rejects codes the device didn't register before sending them
+    pub fn handle(&self) -> DeviceHandle {
+        DeviceHandle::new(
+            self.sender.clone(),
+            self.schedule_ids.clone(),
+            self.capabilities.clone(),
+            self.timing.profile().clone(),
+            self.human_motion.clone(),
+            self.motion_rate,
+            self.max_gradual_step,
+        )
     }
 
     #[inline]
-    pub fn smooth_move_mouse(&mut self, x: Coord, y: Coord) -> Result<()> {
-        self.gradual_move_mouse_raw(x, y)
+    pub fn writing_interval(&self) -> Duration {
+        self.writing_interval
     }
 
-    #[inline]
-    pub fn buffered_gradual_move_mouse(&mut self, x: Coord, y: Coord) -> Vec<EventParams> {
-        let mut write_buffer: Vec<EventParams> = vec![];
-        let gradual_move = GradualMove::calculate(x, y);
-        
-        for _ in 0..gradual_move.both_move {
-            write_buffer.extend(self.buffered_move_mouse(gradual_move.x_direction, gradual_move.y_direction));
-        }
-        for _ in 0..gradual_move.move_only_x {
-            write_buffer.extend(self.buffered_move_mouse_x(gradual_move.x_direction));
-        }
-        for _ in 0..gradual_move.move_only_y {
-            write_buffer.extend(self.buffered_move_mouse_y(gradual_move.y_direction));
-        }
-        
-        write_buffer
+    // wakes up every `writing_interval` even when idle: up to one interval of added latency
+    // and 1000 wake-ups per second with the default 1 ms interval
+    pub fn flush_channel_every_interval(self) -> FlushHandle {
+        self.flush_channel_every_interval_with(|_| {})
     }
 
-    #[inline]
-    pub fn move_mouse_x(&mut self, x: Coord) -> EmptyResult {
-        self.write_batch(&[
-            (EV_REL, REL_X, x),
-            SYN_PARAMS
-        ])
+    // `on_error` is called from the flush thread right before it stops
+    pub fn flush_channel_every_interval_with<F>(self, on_error: F) -> FlushHandle
+    where
+        F: FnMut(&FlushError) + Send + 'static,
+    {
+        self.spawn_flush_thread(None, on_error)
     }
 
-    #[inline]
-    pub fn move_mouse_y(&mut self, y: Coord) -> EmptyResult {
-        self.write_batch(&[
-            (EV_REL, REL_Y, -y),
-            SYN_PARAMS
-        ])
+    // sleeps until an event arrives, then waits up to `coalesce_window` for the rest of the burst
+    // and writes it as one frame. no wake-ups while idle, added latency is `coalesce_window`.
+    // only scheduled events and flings wake the thread up on their own
+    pub fn flush_channel_blocking(self, coalesce_window: Duration) -> FlushHandle {
+        self.flush_channel_blocking_with(coalesce_window, |_| {})
     }
 
-    #[inline]
-    pub fn move_mouse(&mut self, x: Coord, y: Coord) -> EmptyResult {
-        self.write_batch(&[
-            (EV_REL, REL_X, x),
-            (EV_REL, REL_Y, -y),
-            SYN_PARAMS
-        ])
+    pub fn flush_channel_blocking_with<F>(self, coalesce_window: Duration, on_error: F) -> FlushHandle
+    where
+        F: FnMut(&FlushError) + Send + 'static,
+    {
+        self.spawn_flush_thread(Some(coalesce_window), on_error)
     }
 
-    // #[inline]
-    // pub fn move_mouse_with_options(&mut self, x: Coord, y: Coord, buffered: bool, gradual_move: bool, raw_operations:bool) -> EmptyResult {
-    //     let (mouse_x, mouse_y, mouse) = match buffered {
-    //         true => {
-    //             (
-    //                 Self::buffered_move_mouse_x,
-    //                 Self::buffered_move_mouse_y,
-    //                 Self::buffered_move_mouse,
-    //             )
-    //         }
-    //         false => {
-    //             match raw_operations {
-    //                 true => {
-    //                     (
-    //                         Self::move_mouse_raw_x,
-    //                         Self::move_mouse_raw_y,
-    //                         Self::move_mouse_raw,
-    //                     )
-    //                 }
-    //                 false => {
-    //                     (
-    //                         Self::move_mouse_x,
-    //                         Self::move_mouse_y,
-    //                         Self::move_mouse,
-    //                     )
-    //                 }
-    //             }
-    //         }
-    //     };
-    //     match gradual_move {
-    //         true => {
-    //             
-    //         }
-    //         false => {}
-    //     }
-    // }
+    fn spawn_flush_thread<F>(mut self, coalesce_window: Option<Duration>, mut on_error: F) -> FlushHandle
+    where
+        F: FnMut(&FlushError) + Send + 'static,
+    {
+        let (stop_sender, mut stop) = bounded(1);
+        let error = Arc::new(Mutex::new(None));
+        let thread_error = error.clone();
+        let writing_interval = self.writing_interval;
 
-    #[inline]
-    pub fn scroll_raw_x(&mut self, value: Coord) -> EmptyResult {
-        self.write(EV_REL, REL_HWHEEL, value)
+        let mut tick = move || {
+            let result = match coalesce_window {
+                Some(window) => self.flush_blocking(window, &mut stop),
+                None => self.flush_polling(&stop),
+            };
+
+            result.unwrap_or_else(|err| {
+                let err = FlushError::new(err.as_ref());
+                on_error(&err);
+                *thread_error.lock().unwrap() = Some(err);
+                // the device is dropped with the closure, which disconnects every sender
+                ControlFlow::Break(())
+            })
+        };
+
+        let thread = match coalesce_window {
+            Some(_) => thread::spawn(move || while tick().is_continue() {}),
+            None => spawn_interval_loop(writing_interval, tick),
+        };
+
+        FlushHandle::new(stop_sender, error, thread)
     }
 
-    #[inline]
-    pub fn scroll_raw_y(&mut self, value: Coord) -> EmptyResult {
-        self.write(EV_REL, REL_WHEEL, value)
+    fn flush_polling(&mut self, stop: &Receiver<()>) -> Result<ControlFlow<()>> {
+        // a disconnected stop channel means the handle was dropped, the thread keeps running
+        if stop.try_recv().is_ok() {
+            self.drain_channel()?;
+            return Ok(ControlFlow::Break(()));
+        }
+        self.write_events_from_channel()?;
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn flush_blocking(&mut self, coalesce_window: Duration, stop: &mut Receiver<()>) -> Result<ControlFlow<()>> {
+        let timer = match self.next_wakeup() {
+            Some(deadline) => crossbeam_channel::at(deadline),
+            None => crossbeam_channel::never(),
+        };
+        select! {
+            recv(self.receiver.inner()) -> event => {
+                let incoming = &mut self.flush_buffers.incoming;
+                if let Some(event) = event.ok().and_then(|event| self.receiver.accept(event)) {
+                    incoming.push(event);
+                }
+                let coalesce_until = Instant::now() + coalesce_window;
+                while let Some(event) = self.receiver.recv_deadline(coalesce_until) {
+                    incoming.push(event);
+                }
+            }
+            recv(stop) -> signal => match signal {
+                Ok(()) => {
+                    self.drain_channel()?;
+                    return Ok(ControlFlow::Break(()));
+                }
+                // the handle was dropped, the thread keeps running
+                Err(_) => *stop = crossbeam_channel::never(),
+            },
+            recv(timer) -> _ => {}
+        }
+
+        self.write_events()?;
+        Ok(ControlFlow::Continue(()))
+    }
+
+    // the next scheduled event, fling frame or service tick
+    fn next_wakeup(&self) -> Option<Instant> {
+        let scheduled = self.scheduled.peek().map(|Reverse(next)| next.deadline);
+        let fling = self.fling.map(|(_, last_tick)| last_tick + self.writing_interval);
+        let services = self.services.iter().filter_map(|(_, deadline)| *deadline);
+
+        scheduled.into_iter().chain(fling).chain(services).min()
+    }
+
+    // writes whatever is left without waiting for the deadlines, every scheduled event in its own frame
+    fn drain_channel(&mut self) -> EmptyResult {
+        self.write_events_from_channel()?;
+        self.fling = None;
+        self.end_services();
+
+        let mut batch = Vec::new();
+        while let Some(Reverse(scheduled)) = self.scheduled.pop() {
+            self.push_frame(scheduled.frame.commands(), &mut batch);
+        }
+        self.write_frames(&batch, &mut Vec::new())
     }
 
     #[inline]
-    pub fn buffered_scroll_x(&mut self, value: Coord) -> Vec<EventParams> {
-        vec![
-            (EV_REL, REL_HWHEEL, value),
-            SYN_PARAMS
-        ]
+    fn write_events_from_channel(&mut self) -> EmptyResult {
+        self.flush_buffers.incoming.extend(self.receiver.try_iter());
+        self.write_events()
+    }
+
+    // writes the events collected in `flush_buffers.incoming`,
+    // the buffers are put back empty so their capacity is reused by the next flush
+    fn write_events(&mut self) -> EmptyResult {
+        let mut buffers = mem::take(&mut self.flush_buffers);
+        let result = self.write_buffered_events(&mut buffers);
+        buffers.clear();
+        self.flush_buffers = buffers;
+        result
     }
 
+    fn write_buffered_events(&mut self, buffers: &mut FlushBuffers) -> EmptyResult {
+        // immediate frames join the scheduled ones with the time they were sent as their deadline,
+        // so a frame is never written before one that was due earlier, e.g. a press before its scheduled release.
+        // a deadline that had passed when the frame was sent counts as the time it was sent
+        for (sent_at, event) in buffers.incoming.drain(..) {
+            self.accept_event(sent_at, event);
+        }
+
+        // services run on this thread, what they push is handled as if it had just been received
+        let now = Instant::now();
+        let emitted = &mut buffers.emitted;
+        self.services.retain_mut(|(service, deadline)| {
+            if deadline.is_none_or(|deadline| deadline > now) {
+                return true;
+            }
+            match service.run(now, emitted) {
+                ControlFlow::Continue(next) => {
+                    *deadline = next;
+                    true
+                }
+                ControlFlow::Break(()) => false,
+            }
+        });
+        for event in emitted.drain(..) {
+            self.accept_event(now, event);
+        }
+
+        let batch = &mut buffers.batch;
+
+        while let Some(Reverse(next)) = self.scheduled.peek() {
+            if next.deadline > now {
+                break;
+            }
+            let Reverse(scheduled) = self.scheduled.pop().unwrap();
+            // scrolls sent after the fling started cancel it once they are due, scheduled ones included
+            if scheduled.sequence >= self.fling_sequence && scheduled.frame.has_scroll() {
+                self.fling = None;
+            }
+            self.push_frame(scheduled.frame.commands(), batch);
+        }
+
+        if let Some((x, y)) = self.advance_fling(now) {
+            self.push_frame(&[ChannelCommand::Scroll(x, y)], batch);
+        }
+
+        self.write_frames(batch, &mut buffers.coalesced)
+    }
+
+    fn accept_event(&mut self, sent_at: Instant, event: ChannelEvent) {
+        match event {
+            ChannelEvent::Now(frame) => self.schedule(None, sent_at, frame),
+            ChannelEvent::At(deadline, frame) => self.schedule(None, deadline.max(sent_at), frame),
+            ChannelEvent::Scheduled(id, deadline, frame) => self.schedule(Some(id), deadline.max(sent_at), frame),
+            ChannelEvent::Cancel(id) => {
+                self.scheduled.retain(|Reverse(scheduled)| scheduled.id != Some(id));
+            }
+            ChannelEvent::Fling(fling) => {
+                self.fling = Some((fling, Instant::now()));
+                self.fling_sequence = self.scheduled_count;
+            }
+            // a stopped service is removed the next time it would run, which is now
+            ChannelEvent::Service(service) => match self.services.iter_mut().find(|(known, _)| *known == service) {
+                Some((_, deadline)) => *deadline = Some(sent_at),
+                None => self.services.push((service, Some(sent_at))),
+            },
+        }
+    }
+
+    // the handles of the services report that they are no longer running
+    fn end_services(&mut self) {
+        for (service, _) in self.services.drain(..) {
+            service.end();
+        }
+    }
+
+    // every frame is already terminated, all of them go out in a single write
     #[inline]
-    pub fn buffered_scroll_y(&mut self, value: Coord) -> Vec<EventParams> {
-        vec![
-            (EV_REL, REL_WHEEL, value),
-            SYN_PARAMS
-        ]
+    fn write_frames(&mut self, batch: &[EventParams], coalesced: &mut Vec<EventParams>) -> EmptyResult {
+        let batch = match self.coalescing {
+            true => {
+                coalesce(batch, coalesced);
+                coalesced.as_slice()
+            }
+            false => batch,
+        };
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.write_batch(batch)
     }
 
     #[inline]
-    pub fn scroll_x(&mut self, value: Coord) -> EmptyResult {
-        self.write_batch(&[
-            (EV_REL, REL_HWHEEL, value),
-            SYN_PARAMS
-        ])
+    pub fn coalescing(&self) -> bool {
+        self.coalescing
+    }
+
+    // merges motion-only frames of a flush and sums the deltas of each axis,
+    // key transitions keep their place relative to motion. off by default
+    pub fn set_coalescing(&mut self, enabled: bool) {
+        self.coalescing = enabled;
     }
 
+    // deadlines are `Instant`s, so the queue follows the monotonic clock
     #[inline]
-    pub fn gradual_scroll_raw(&mut self, x: Coord, y: Coord) -> Result<()> {
-        let gradual_move = GradualMove::calculate(x, y);
+    fn schedule(&mut self, id: Option<ScheduleId>, deadline: Instant, frame: Frame) {
+        let sequence = self.scheduled_count;
+        self.scheduled.push(Reverse(ScheduledFrame { deadline, sequence, id, frame }));
+        self.scheduled_count += 1;
+    }
 
-        for _ in 0..gradual_move.both_move {
-            self.scroll_raw_x(gradual_move.x_direction)?;
-            self.scroll_raw_y(gradual_move.y_direction)?;
+    // frames that map to nothing, e.g. a sub-pixel move, are skipped without a SYN_REPORT
+    fn push_frame(&mut self, commands: &[ChannelCommand], batch: &mut Vec<EventParams>) {
+        let start = batch.len();
+        for command in commands {
+            self.push_command(*command, batch);
+        }
+        if batch.len() > start {
+            batch.push(SYN_PARAMS);
         }
-        for _ in 0..gradual_move.move_only_x {
-            self.scroll_raw_x(gradual_move.x_direction)?;
+    }
+
+    fn push_command(&mut self, command: ChannelCommand, batch: &mut Vec<EventParams>) {
+        let (events, (x, y)) = match command {
+            ChannelCommand::Event(event) => {
+                batch.push(event);
+                return;
+            }
+            ChannelCommand::Move(x, y) => ((REL_X, REL_Y), self.map_motion(x, y)),
+            ChannelCommand::Scroll(x, y) => {
+                batch.extend(self.scroll_events(x, y).into_iter().filter(|event| event.2 != 0));
+                return;
+            }
+        };
+
+        if x != 0 {
+            batch.push((EV_REL, events.0, x));
         }
-        for _ in 0..gradual_move.move_only_y {
-            self.scroll_raw_y(gradual_move.y_direction)?;
+        if y != 0 {
+            batch.push((EV_REL, events.1, y));
         }
-        self.synchronize()?;
+    }
 
+    #[inline]
+    pub fn write_batch(&mut self, batch: &[EventParams]) -> EmptyResult{
+        // only what reached the kernel counts as pressed or moved
+        if let Err(err) = write_whole(&self.file, self.buffer.encode(batch), self.write_timeout) {
+            self.track_batch(&batch[..err.committed()]);
+            return Err(Box::new(err));
+        }
+        self.track_batch(batch);
         Ok(())
     }
 
     #[inline]
-    pub fn gradual_scroll(&mut self, x: Coord, y: Coord) -> Result<()> {
-        let gradual_move = GradualMove::calculate(x, y);
+    pub fn write_timeout(&self) -> Duration {
+        self.write_timeout
+    }
 
-        for _ in 0..gradual_move.both_move {
-            self.scroll_x(gradual_move.x_direction)?;
-            self.scroll_y(gradual_move.y_direction)?;
-        }
-        for _ in 0..gradual_move.move_only_x {
-            self.scroll_x(gradual_move.x_direction)?;
+    // how long a write waits for a busy device before giving up with `WriteError::Timeout`
+    #[inline]
+    pub fn set_write_timeout(&mut self, timeout: Duration) {
+        self.write_timeout = timeout;
+    }
+
+    // call once the whole batch reached the kernel
+    #[inline]
+    pub(crate) fn track_batch(&mut self, batch: &[EventParams]) {
+        for event in batch {
+            self.track_state(event);
         }
-        for _ in 0..gradual_move.move_only_y {
-            self.scroll_y(gradual_move.y_direction)?;
+    }
+
+    #[cfg(feature = "async")]
+    #[inline]
+    pub(crate) fn file(&self) -> &File {
+        &self.file
+    }
+
+    #[inline]
+    fn write(&mut self, kind: u16, code: u16, value: i32) -> EmptyResult {
+        // let content = convert_event_for_writing(kind, code, value);
+        // self.file.write_all(content.as_slice())?;
+
+        let input_event = input_event {
+            time: FIXED_TIME,
+            kind,
+            code,
+            value,
+        };
+
+        unsafe {
+            // gettimeofday(&mut input_event.time, ptr::null_mut());
+
+            let ptr = &input_event as *const _ as *const u8;
+            let size = mem::size_of_val(&input_event);
+            let content = slice::from_raw_parts(ptr, size);
+            write_whole(&self.file, content, self.write_timeout)?;
         }
+        self.track_state(&(kind, code, value));
 
         Ok(())
     }
 
+    pub fn emit(&mut self, events: &[InputEvent]) -> EmptyResult {
+        self.write_built_batch(|device, batch| {
+            for event in events {
+                device.ensure_supported(event)?;
+                batch.push(EventParams::from(*event));
+            }
+            batch.push(SYN_PARAMS);
+            Ok(())
+        })
+    }
+
+    // builds the batch in the reusable buffer of the device instead of a new Vec
+    fn write_built_batch(
+        &mut self,
+        build: impl FnOnce(&mut Self, &mut Vec<EventParams>) -> EmptyResult,
+    ) -> EmptyResult {
+        let mut batch = mem::take(&mut self.flush_buffers.batch);
+        let result = build(self, &mut batch).and_then(|()| self.write_batch(&batch));
+        batch.clear();
+        self.flush_buffers.batch = batch;
+        result
+    }
+
     #[inline]
-    pub fn smooth_scroll(&mut self, x: Coord, y: Coord) -> Result<()> {
-        self.gradual_scroll_raw(x, y)
+    pub(crate) fn track_state(&mut self, event: &EventParams) {
+        match *event {
+            (EV_KEY, code, 0) => {
+                self.pressed.remove(&Key(code));
+            }
+            (EV_KEY, code, _) => {
+                self.pressed.insert(Key(code));
+            }
+            (EV_ABS, ABS_X, value) => self.abs_position.0 = value,
+            (EV_ABS, ABS_Y, value) => self.abs_position.1 = value,
+            _ => {}
+        }
+    }
+
+    #[inline(always)]
+    pub fn synchronize(&mut self) -> EmptyResult {
+        self.write(EV_SYN, SYN_REPORT, 0)
     }
 
     #[inline]
-    pub fn buffered_gradual_scroll(&mut self, x: Coord, y: Coord) -> Vec<EventParams> {
-        let mut write_buffer: Vec<EventParams> = vec![];
-        let gradual_move = GradualMove::calculate(x, y);
+    pub fn move_mouse_raw_x(&mut self, x: Coord) -> EmptyResult {
+        self.move_mouse_raw(x, 0)
+    }
+
+    #[inline]
+    pub fn move_mouse_raw_y(&mut self, y: Coord) -> EmptyResult {
+        self.move_mouse_raw(0, y)
+    }
+
+    #[inline]
+    pub fn move_mouse_raw(&mut self, x: Coord, y: Coord) -> EmptyResult {
+        self.ensure_rel(RelAxis::X)?;
+        self.ensure_rel(RelAxis::Y)?;
+        let (x, y) = self.map_motion(x as f64, y as f64);
+        self.write_nonzero([(EV_REL, REL_X, x), (EV_REL, REL_Y, y)], false)
+    }
 
-        for _ in 0..gradual_move.both_move {
-            write_buffer.extend(self.buffered_scroll_x(gradual_move.x_direction));
-            write_buffer.extend(self.buffered_scroll_y(gradual_move.y_direction));
+    // motion and scroll events of axes that don't move are left out and nothing is written
+    // when none of them moves, `sync` ends the frame with SYN_REPORT
+    fn write_nonzero<const N: usize>(&mut self, events: [EventParams; N], sync: bool) -> EmptyResult {
+        let mut batch = [SYN_PARAMS; 5];
+        let mut count = 0;
+        for event in events.into_iter().filter(|event| event.2 != 0) {
+            batch[count] = event;
+            count += 1;
         }
-        for _ in 0..gradual_move.move_only_x {
-            write_buffer.extend(self.buffered_scroll_x(gradual_move.x_direction));
+        if count == 0 {
+            return Ok(());
         }
-        for _ in 0..gradual_move.move_only_y {
-            write_buffer.extend(self.buffered_scroll_y(gradual_move.y_direction));
+        if sync {
+            count += 1;
         }
-
-        write_buffer
+        self.write_batch(&batch[..count])
     }
 
+    // the helpers returning a `Vec` only encode: y counts up, every axis is included
+    // and neither the coordinate system nor the sub-pixel remainders are touched
     #[inline]
-    pub fn scroll_y(&mut self, value: Coord) -> EmptyResult {
-        self.write_batch(&[
-            (EV_REL, REL_WHEEL, value),
+    pub fn buffered_move_mouse_x(&mut self, x: Coord) -> Vec<EventParams> {
+        vec![
+            (EV_REL, REL_X, x),
             SYN_PARAMS
-        ])
+        ]
     }
 
     #[inline]
-    pub fn buffered_press(&mut self, button: Button) -> Vec<EventParams>  {
+    pub fn buffered_move_mouse_y(&mut self, y: Coord) -> Vec<EventParams> {
         vec![
-            (EV_KEY, button, 1),
+            (EV_REL, REL_Y, -y),
             SYN_PARAMS
         ]
     }
 
     #[inline]
-    pub fn buffered_release(&mut self, button: Button) -> Vec<EventParams> {
+    pub fn buffered_move_mouse(&mut self, x: Coord, y: Coord) -> Vec<EventParams> {
         vec![
-            (EV_KEY, button, 0),
+            (EV_REL, REL_X, x),
+            (EV_REL, REL_Y, -y),
             SYN_PARAMS
         ]
     }
 
+    // the `_into` helpers append to `write_buffer`, reusing one buffer keeps them from allocating.
+    // they go through the coordinate system and the remainders like `move_mouse`,
+    // axes that end up not moving are left out, a move of nothing appends nothing
+    pub fn buffered_move_mouse_into(&mut self, x: Coord, y: Coord, write_buffer: &mut Vec<EventParams>) {
+        self.push_frame(&[ChannelCommand::Move(x as f64, y as f64)], write_buffer);
+    }
+
     #[inline]
-    pub fn press(&mut self, button: Button) -> EmptyResult {
-        self.write_batch(&[
-            (EV_KEY, button, 1),
-            SYN_PARAMS
-        ])
+    pub fn gradual_move_mouse_raw(&mut self, x: Coord, y: Coord) -> Result<()> {
+        for (step_x, step_y) in LineSteps::new(x, y, self.max_gradual_step) {
+            match (step_x, step_y) {
+                (_, 0) => self.move_mouse_raw_x(step_x)?,
+                (0, _) => self.move_mouse_raw_y(step_y)?,
+                _ => self.move_mouse_raw(step_x, step_y)?,
+            }
+        }
+        self.synchronize()?;
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn gradual_move_mouse(&mut self, x: Coord, y: Coord) -> Result<()> {
+        for (step_x, step_y) in LineSteps::new(x, y, self.max_gradual_step) {
+            match (step_x, step_y) {
+                (_, 0) => self.move_mouse_x(step_x)?,
+                (0, _) => self.move_mouse_y(step_y)?,
+                _ => self.move_mouse(step_x, step_y)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn smooth_move_mouse(&mut self, x: Coord, y: Coord) -> Result<()> {
+        self.gradual_move_mouse_raw(x, y)
+    }
+
+    pub fn move_mouse_over(&mut self, x: Coord, y: Coord, duration: Duration, easing: Easing) -> EmptyResult {
+        self.play_motion(MotionFrames::new(x, y, duration, easing, self.motion_rate))
+    }
+
+    pub fn move_mouse_human(&mut self, x: Coord, y: Coord) -> EmptyResult {
+        let trajectory = self.human_trajectory(x, y);
+        self.play_motion(trajectory)
+    }
+
+    #[inline]
+    pub(crate) fn human_trajectory(&mut self, x: Coord, y: Coord) -> Trajectory {
+        let seed = self.human_rng.next_u64();
+        Trajectory::new(x, y, &self.human_motion, seed)
+    }
+
+    // frames are (offset from now, dx, dy), the calling thread sleeps until each one is due
+    pub fn play_motion(&mut self, frames: impl IntoIterator<Item = (Duration, Coord, Coord)>) -> EmptyResult {
+        self.ensure_rel(RelAxis::X)?;
+        self.ensure_rel(RelAxis::Y)?;
+
+        let start = Instant::now();
+
+        for (offset, dx, dy) in frames {
+            if let Some(remaining) = (start + offset).checked_duration_since(Instant::now()) {
+                sleep(remaining);
+            }
+            self.move_mouse(dx, dy)?;
+        }
+        Ok(())
+    }
+
+    pub fn drag(&mut self, button: impl Into<Button>, x: Coord, y: Coord, options: &DragOptions) -> EmptyResult {
+        let (step_x, step_y) = options.threshold_step(x, y);
+        let motion = MotionFrames::new(x - step_x, y - step_y, options.duration, options.easing, self.motion_rate);
+
+        self.drag_with(button.into(), options, |device| {
+            if step_x != 0 || step_y != 0 {
+                device.move_mouse(step_x, step_y)?;
+            }
+            device.play_motion(motion)
+        })
+    }
+
+    // absolute device equivalent of `drag`, moves from the current position to (x, y)
+    pub fn drag_to(&mut self, button: impl Into<Button>, x: Coord, y: Coord, options: &DragOptions) -> EmptyResult {
+        // the threshold is in device units here
+        let (start_x, start_y) = self.abs_position;
+        let (step_x, step_y) = options.threshold_step(x - start_x, y - start_y);
+
+        self.drag_with(button.into(), options, |device| {
+            if step_x != 0 || step_y != 0 {
+                device.move_to(start_x + step_x, start_y + step_y)?;
+            }
+            device.move_to_over(x, y, options.duration, options.easing)
+        })
+    }
+
+    fn drag_with<F>(&mut self, button: Button, options: &DragOptions, movement: F) -> EmptyResult
+    where
+        F: FnOnce(&mut Self) -> EmptyResult,
+    {
+        // the guard releases the button if the movement fails
+        let mut guard = self.hold(button)?;
+        sleep(options.start_delay);
+        movement(&mut guard)?;
+        sleep(options.end_delay);
+        guard.release(button)
     }
 
     #[inline]
-    pub fn release(&mut self, button: Button) -> EmptyResult {
+    pub fn abs_position(&self) -> (Coord, Coord) {
+        self.abs_position
+    }
+
+    pub fn abs_range(&self, axis: AbsAxis) -> Option<(Coord, Coord)> {
+        if !self.capabilities.has_abs(axis) {
+            return None;
+        }
+        let index = axis.code() as usize;
+        Some((self.def.absmin[index], self.def.absmax[index]))
+    }
+
+    pub fn move_to(&mut self, x: Coord, y: Coord) -> EmptyResult {
+        let ((min_x, max_x), (min_y, max_y)) = self.abs_ranges()?;
+
         self.write_batch(&[
-            (EV_KEY, button, 0),
+            (EV_ABS, ABS_X, x.clamp(min_x, max_x)),
+            (EV_ABS, ABS_Y, y.clamp(min_y, max_y)),
             SYN_PARAMS
         ])
     }
 
-    pub fn click(&mut self, button: Button) -> EmptyResult {
-        self.press(button)?;
-        sleep(SLEEP_BEFORE_RELEASE); // required to preserve typing order
-        self.release(button)
+    #[inline]
+    pub fn screen_geometry(&self) -> Option<&ScreenGeometry> {
+        self.screen_geometry.as_ref()
     }
-}
 
-impl Drop for VirtualDevice {
-    fn drop(&mut self) {
-        unsafe {
-            ui_dev_destroy(self.file.as_raw_fd());
-        }
+    // without a geometry the ABS range is treated as a single screen
+    pub fn set_screen_geometry(&mut self, geometry: Option<ScreenGeometry>) {
+        self.screen_geometry = geometry;
+    }
+
+    #[inline]
+    fn abs_ranges(&self) -> Result<((Coord, Coord), (Coord, Coord))> {
+        Ok((
+            self.abs_range(AbsAxis::X).ok_or("Virtual device has no absolute X axis")?,
+            self.abs_range(AbsAxis::Y).ok_or("Virtual device has no absolute Y axis")?,
+        ))
     }
-}
\ No newline at end of file
+
+    // (x, y) are logical points relative to the top left corner of the monitor
+    pub fn move_to_monitor(&mut self, idx: usize, x: f64, y: f64) -> EmptyResult {
+        self.move_to_desktop(idx, |monitor| monitor.to_desktop(x, y))
+    }
+
+    // (0, 0) is the top left and (100, 100) the bottom right corner of the monitor
+    pub fn move_to_monitor_percent(&mut self, idx: usize, x: f64, y: f64) -> EmptyResult {
+        self.move_to_desktop(idx, |monitor| monitor.percent_to_desktop(x, y))
+    }
+
+    fn move_to_desktop<F>(&mut self, idx: usize, to_desktop: F) -> EmptyResult
+    where
+        F: FnOnce(&Monitor) -> (f64, f64),
+    {
+        let (range_x, range_y) = self.abs_ranges()?;
+        let geometry = self.screen_geometry.as_ref().ok_or("Screen geometry is not set")?;
+
+        let (desktop_x, desktop_y) = to_desktop(geometry.monitor(idx)?);
+        let (x, y) = geometry.desktop_to_abs(desktop_x, desktop_y, range_x, range_y);
+        self.move_to(x, y)
+    }
+
+    // percentages of the whole ABS range, spanning every monitor
+    pub fn move_to_percent(&mut self, x: f64, y: f64) -> EmptyResult {
+        let (range_x, range_y) = self.abs_ranges()?;
+        self.move_to(percent_to_range(x, range_x), percent_to_range(y, range_y))
+    }
+
+    pub fn move_to_over(&mut self, x: Coord, y: Coord, duration: Duration, easing: Easing) -> EmptyResult {
+        self.ensure_abs(AbsAxis::X)?;
+        self.ensure_abs(AbsAxis::Y)?;
+
+        let (mut current_x, mut current_y) = self.abs_position;
+        let start = Instant::now();
+
+        for (offset, dx, dy) in MotionFrames::new(x - current_x, y - current_y, duration, easing, self.motion_rate) {
+            if let Some(remaining) = (start + offset).checked_duration_since(Instant::now()) {
+                sleep(remaining);
+            }
+            current_x += dx;
+            current_y += dy;
+            self.move_to(current_x, current_y)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn human_motion(&self) -> &HumanMotion {
+        &self.human_motion
+    }
+
+    pub fn set_human_motion(&mut self, motion: HumanMotion) {
+        self.human_rng = Rng::new(motion.seed);
+        self.human_motion = motion;
+    }
+
+    #[inline]
+    pub fn max_gradual_step(&self) -> Coord {
+        self.max_gradual_step
+    }
+
+    // largest distance a single event of gradual_* and buffered_gradual_* moves and scrolls may cover
+    pub fn set_max_gradual_step(&mut self, max_step: Coord) {
+        self.max_gradual_step = max_step.max(1);
+    }
+
+    #[inline]
+    pub fn motion_rate(&self) -> u32 {
+        self.motion_rate
+    }
+
+    pub fn set_motion_rate(&mut self, rate_hz: u32) {
+        self.motion_rate = rate_hz.max(1);
+    }
+
+    #[inline]
+    pub fn buffered_gradual_move_mouse(&mut self, x: Coord, y: Coord) -> Vec<EventParams> {
+        let mut write_buffer = Vec::new();
+        for (step_x, step_y) in LineSteps::new(x, y, self.max_gradual_step) {
+            write_buffer.extend(match (step_x, step_y) {
+                (_, 0) => self.buffered_move_mouse_x(step_x),
+                (0, _) => self.buffered_move_mouse_y(step_y),
+                _ => self.buffered_move_mouse(step_x, step_y),
+            });
+        }
+        write_buffer
+    }
+
+    pub fn buffered_gradual_move_mouse_into(&mut self, x: Coord, y: Coord, write_buffer: &mut Vec<EventParams>) {
+        for (step_x, step_y) in LineSteps::new(x, y, self.max_gradual_step) {
+            self.buffered_move_mouse_into(step_x, step_y, write_buffer);
+        }
+    }
+
+    #[inline]
+    pub fn move_mouse_x(&mut self, x: Coord) -> EmptyResult {
+        self.move_mouse(x, 0)
+    }
+
+    #[inline]
+    pub fn move_mouse_y(&mut self, y: Coord) -> EmptyResult {
+        self.move_mouse(0, y)
+    }
+
+    #[inline]
+    pub fn move_mouse(&mut self, x: Coord, y: Coord) -> EmptyResult {
+        self.ensure_rel(RelAxis::X)?;
+        self.ensure_rel(RelAxis::Y)?;
+        let (x, y) = self.map_motion(x as f64, y as f64);
+        self.write_nonzero([(EV_REL, REL_X, x), (EV_REL, REL_Y, y)], true)
+    }
+
+    #[inline]
+    pub fn move_mouse_f(&mut self, x: f64, y: f64) -> EmptyResult {
+        self.ensure_rel(RelAxis::X)?;
+        self.ensure_rel(RelAxis::Y)?;
+        let (x, y) = self.map_motion(x, y);
+        self.write_nonzero([(EV_REL, REL_X, x), (EV_REL, REL_Y, y)], true)
+    }
+
+    #[inline]
+    pub fn scroll_f(&mut self, x: f64, y: f64) -> EmptyResult {
+        self.ensure_scroll()?;
+        self.fling = None;
+        self.write_scroll(x, y)
+    }
+
+    // skips the write when nothing adds up to a whole unit yet
+    fn write_scroll(&mut self, x: f64, y: f64) -> EmptyResult {
+        let events = self.scroll_events(x, y);
+        self.write_nonzero(events, true)
+    }
+
+    // velocities are in wheel units per second, `friction` is the exponential decay rate.
+    // starts the fling and returns right away, the guard writes it with `tick` or `run`.
+    // any other scroll or dropping the guard cancels it, `detach` leaves it to `tick_fling` or the flush thread
+    #[inline]
+    pub fn fling_scroll(&mut self, velocity_x: f64, velocity_y: f64, friction: f64) -> Result<FlingGuard<'_>> {
+        self.fling_scroll_with(velocity_x, velocity_y, Decay::Exponential(friction))
+    }
+
+    pub fn fling_scroll_with(&mut self, velocity_x: f64, velocity_y: f64, decay: Decay) -> Result<FlingGuard<'_>> {
+        self.ensure_scroll()?;
+        self.fling = Some((Fling::new(velocity_x, velocity_y, decay)?, Instant::now()));
+        Ok(FlingGuard::new(self))
+    }
+
+    // writes the distance covered since the previous tick, returns false once the fling has stopped.
+    // call it at the frame rate the scroll should have, e.g. every `1 / motion_rate`
+    pub fn tick_fling(&mut self) -> Result<bool> {
+        if let Some((x, y)) = self.advance_fling(Instant::now()) {
+            self.write_scroll(x, y)?;
+        }
+        Ok(self.fling.is_some())
+    }
+
+    #[inline]
+    pub fn is_flinging(&self) -> bool {
+        self.fling.is_some()
+    }
+
+    #[inline]
+    pub fn cancel_fling(&mut self) {
+        self.fling = None;
+    }
+
+    fn advance_fling(&mut self, now: Instant) -> Option<(f64, f64)> {
+        let (mut fling, last_tick) = self.fling?;
+        let distance = fling.advance(now.saturating_duration_since(last_tick));
+        self.fling = (!fling.is_finished()).then_some((fling, now));
+        Some(distance)
+    }
+
+    // coordinate system is applied here, the remainder of each axis is carried over to the next event
+    #[inline]
+    pub(crate) fn map_motion(&mut self, x: f64, y: f64) -> (Coord, Coord) {
+        let (x, y) = self.coordinate_system.to_screen(x, y);
+        (
+            accumulate(&mut self.rel_remainders, RelAxis::X, x),
+            accumulate(&mut self.rel_remainders, RelAxis::Y, y),
+        )
+    }
+
+    #[inline]
+    pub(crate) fn map_scroll(&mut self, x: f64, y: f64) -> (Coord, Coord) {
+        let (x, y) = self.coordinate_system.to_screen(x, y);
+        // the wheel counts up while screen space counts down
+        (
+            accumulate(&mut self.rel_remainders, RelAxis::HWHEEL_HI_RES, x),
+            accumulate(&mut self.rel_remainders, RelAxis::WHEEL_HI_RES, -y),
+        )
+    }
+
+    // the hi-res events and the legacy notches a hi-res wheel sends next to them,
+    // applications without hi-res support see a notch every 120 units
+    #[inline]
+    pub(crate) fn scroll_events(&mut self, x: f64, y: f64) -> [EventParams; 4] {
+        let (x, y) = self.map_scroll(x, y);
+        let notches_x = accumulate(&mut self.rel_remainders, RelAxis::HWHEEL_NOTCH, x as f64 / HI_RES_PER_NOTCH);
+        let notches_y = accumulate(&mut self.rel_remainders, RelAxis::WHEEL_NOTCH, y as f64 / HI_RES_PER_NOTCH);
+        [
+            (EV_REL, REL_HWHEEL_HI_RES, x),
+            (EV_REL, REL_WHEEL_HI_RES, y),
+            (EV_REL, REL_HWHEEL_NOTCH, notches_x),
+            (EV_REL, REL_WHEEL_NOTCH, notches_y),
+        ]
+    }
+
+    #[inline]
+    pub(crate) fn ensure_scroll(&self) -> EmptyResult {
+        self.capabilities.ensure_scroll()
+    }
+
+    #[inline]
+    pub fn coordinate_system(&self) -> &CoordinateSystem {
+        &self.coordinate_system
+    }
+
+    pub fn set_coordinate_system(&mut self, coordinate_system: CoordinateSystem) {
+        self.coordinate_system = coordinate_system;
+        self.rel_remainders = [0.0; REL_CNT as usize];
+    }
+
+    // #[inline]
+    // pub fn move_mouse_with_options(&mut self, x: Coord, y: Coord, buffered: bool, gradual_move: bool, raw_operations:bool) -> EmptyResult {
+    //     let (mouse_x, mouse_y, mouse) = match buffered {
+    //         true => {
+    //             (
+    //                 Self::buffered_move_mouse_x,
+    //                 Self::buffered_move_mouse_y,
+    //                 Self::buffered_move_mouse,
+    //             )
+    //         }
+    //         false => {
+    //             match raw_operations {
+    //                 true => {
+    //                     (
+    //                         Self::move_mouse_raw_x,
+    //                         Self::move_mouse_raw_y,
+    //                         Self::move_mouse_raw,
+    //                     )
+    //                 }
+    //                 false => {
+    //                     (
+    //                         Self::move_mouse_x,
+    //                         Self::move_mouse_y,
+    //                         Self::move_mouse,
+    //                     )
+    //                 }
+    //             }
+    //         }
+    //     };
+    //     match gradual_move {
+    //         true => {
+    //             
+    //         }
+    //         false => {}
+    //     }
+    // }
+
+    #[inline]
+    pub fn scroll_raw_x(&mut self, value: Coord) -> EmptyResult {
+        self.scroll_raw(value, 0)
+    }
+
+    #[inline]
+    pub fn scroll_raw_y(&mut self, value: Coord) -> EmptyResult {
+        self.scroll_raw(0, value)
+    }
+
+    #[inline]
+    pub fn scroll_raw(&mut self, x: Coord, y: Coord) -> EmptyResult {
+        self.ensure_scroll()?;
+        self.fling = None;
+        let events = self.scroll_events(x as f64, y as f64);
+        self.write_nonzero(events, false)
+    }
+
+    // like the moves, the `Vec` helpers only encode the hi-res wheel values as given
+    #[inline]
+    pub fn buffered_scroll_x(&mut self, value: Coord) -> Vec<EventParams> {
+        vec![
+            (EV_REL, REL_HWHEEL_HI_RES, value),
+            SYN_PARAMS
+        ]
+    }
+
+    #[inline]
+    pub fn buffered_scroll_y(&mut self, value: Coord) -> Vec<EventParams> {
+        vec![
+            (EV_REL, REL_WHEEL_HI_RES, value),
+            SYN_PARAMS
+        ]
+    }
+
+    #[inline]
+    pub fn buffered_scroll(&mut self, x: Coord, y: Coord) -> Vec<EventParams> {
+        vec![
+            (EV_REL, REL_HWHEEL_HI_RES, x),
+            (EV_REL, REL_WHEEL_HI_RES, y),
+            SYN_PARAMS
+        ]
+    }
+
+    // only fills the buffer, a running fling goes on until a written scroll cancels it.
+    // goes through the coordinate system like `scroll`, adds the notches and leaves out wheels that are zero
+    pub fn buffered_scroll_into(&mut self, x: Coord, y: Coord, write_buffer: &mut Vec<EventParams>) {
+        self.push_frame(&[ChannelCommand::Scroll(x as f64, y as f64)], write_buffer);
+    }
+
+    #[inline]
+    pub fn scroll_x(&mut self, value: Coord) -> EmptyResult {
+        self.scroll(value, 0)
+    }
+
+    #[inline]
+    pub fn gradual_scroll_raw(&mut self, x: Coord, y: Coord) -> Result<()> {
+        for (step_x, step_y) in LineSteps::new(x, y, self.max_gradual_step) {
+            if step_x != 0 {
+                self.scroll_raw_x(step_x)?;
+            }
+            if step_y != 0 {
+                self.scroll_raw_y(step_y)?;
+            }
+        }
+        self.synchronize()?;
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn gradual_scroll(&mut self, x: Coord, y: Coord) -> Result<()> {
+        for (step_x, step_y) in LineSteps::new(x, y, self.max_gradual_step) {
+            if step_x != 0 {
+                self.scroll_x(step_x)?;
+            }
+            if step_y != 0 {
+                self.scroll_y(step_y)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn smooth_scroll(&mut self, x: Coord, y: Coord) -> Result<()> {
+        self.gradual_scroll_raw(x, y)
+    }
+
+    #[inline]
+    pub fn buffered_gradual_scroll(&mut self, x: Coord, y: Coord) -> Vec<EventParams> {
+        let mut write_buffer = Vec::new();
+        for (step_x, step_y) in LineSteps::new(x, y, self.max_gradual_step) {
+            if step_x != 0 {
+                write_buffer.extend(self.buffered_scroll_x(step_x));
+            }
+            if step_y != 0 {
+                write_buffer.extend(self.buffered_scroll_y(step_y));
+            }
+        }
+        write_buffer
+    }
+
+    pub fn buffered_gradual_scroll_into(&mut self, x: Coord, y: Coord, write_buffer: &mut Vec<EventParams>) {
+        for (step_x, step_y) in LineSteps::new(x, y, self.max_gradual_step) {
+            if step_x != 0 {
+                self.buffered_scroll_into(step_x, 0, write_buffer);
+            }
+            if step_y != 0 {
+                self.buffered_scroll_into(0, step_y, write_buffer);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn scroll_y(&mut self, value: Coord) -> EmptyResult {
+        self.scroll(0, value)
+    }
+
+    #[inline]
+    pub fn scroll(&mut self, x: Coord, y: Coord) -> EmptyResult {
+        self.ensure_scroll()?;
+        self.fling = None;
+        self.write_scroll(x as f64, y as f64)
+    }
+
+    #[inline]
+    pub fn buffered_press(&mut self, button: impl Into<Button>) -> Vec<EventParams> {
+        vec![
+            (EV_KEY, button.into().code(), 1),
+            SYN_PARAMS
+        ]
+    }
+
+    #[inline]
+    pub fn buffered_release(&mut self, button: impl Into<Button>) -> Vec<EventParams> {
+        vec![
+            (EV_KEY, button.into().code(), 0),
+            SYN_PARAMS
+        ]
+    }
+
+    #[inline]
+    pub fn buffered_press_into(&mut self, button: impl Into<Button>, write_buffer: &mut Vec<EventParams>) {
+        write_buffer.extend([(EV_KEY, button.into().code(), 1), SYN_PARAMS]);
+    }
+
+    #[inline]
+    pub fn buffered_release_into(&mut self, button: impl Into<Button>, write_buffer: &mut Vec<EventParams>) {
+        write_buffer.extend([(EV_KEY, button.into().code(), 0), SYN_PARAMS]);
+    }
+
+    #[inline]
+    pub fn press(&mut self, button: impl Into<Button>) -> EmptyResult {
+        let button = button.into();
+        self.ensure_key(button)?;
+        self.write_batch(&[
+            (EV_KEY, button.code(), 1),
+            SYN_PARAMS
+        ])
+    }
+
+    #[inline]
+    pub fn release(&mut self, button: impl Into<Button>) -> EmptyResult {
+        let button = button.into();
+        self.ensure_key(button)?;
+        self.write_batch(&[
+            (EV_KEY, button.code(), 0),
+            SYN_PARAMS
+        ])
+    }
+
+    pub fn click(&mut self, button: impl Into<Button>) -> EmptyResult {
+        let hold = self.timing.hold();
+        self.click_for(button, hold)
+    }
+
+    pub fn click_with(&mut self, button: impl Into<Button>, timing: &mut Timing) -> EmptyResult {
+        self.click_for(button, timing.hold())
+    }
+
+    pub fn click_for(&mut self, button: impl Into<Button>, hold: Duration) -> EmptyResult {
+        let button = button.into();
+        self.press(button)?;
+        sleep(hold); // required to preserve typing order
+        self.release(button)
+    }
+
+    pub fn double_click(&mut self, button: impl Into<Button>) -> EmptyResult {
+        self.multi_click(button, 2)
+    }
+
+    pub fn triple_click(&mut self, button: impl Into<Button>) -> EmptyResult {
+        self.multi_click(button, 3)
+    }
+
+    pub fn multi_click(&mut self, button: impl Into<Button>, count: usize) -> EmptyResult {
+        let mut timing = mem::take(&mut self.timing);
+        let result = self.multi_click_with(button, count, &mut timing);
+        self.timing = timing;
+        result
+    }
+
+    pub fn multi_click_with(&mut self, button: impl Into<Button>, count: usize, timing: &mut Timing) -> EmptyResult {
+        let button = button.into();
+
+        for index in 0..count {
+            if index > 0 {
+                sleep(timing.multi_click_interval());
+            }
+            self.click_for(button, timing.hold())?;
+        }
+        Ok(())
+    }
+
+    pub fn type_text(&mut self, text: &str) -> EmptyResult {
+        let mut timing = mem::take(&mut self.timing);
+        let result = self.type_text_with(text, &mut timing);
+        self.timing = timing;
+        result
+    }
+
+    pub fn type_text_with(&mut self, text: &str, timing: &mut Timing) -> EmptyResult {
+        for keystroke in plan_keystrokes(text, timing)? {
+            sleep(keystroke.delay);
+            if keystroke.shift {
+                self.with_modifiers(&[KEY_LEFTSHIFT], |device| device.click_for(keystroke.key, keystroke.hold))?;
+            } else {
+                self.click_for(keystroke.key, keystroke.hold)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[inline]
+    pub(crate) fn timing_mut(&mut self) -> &mut Timing {
+        &mut self.timing
+    }
+
+    #[inline]
+    pub fn timing_profile(&self) -> &TimingProfile {
+        self.timing.profile()
+    }
+
+    // also used by the static `send_*` clicks of `sender` and its clones,
+    // handles that already exist keep their own profile
+    pub fn set_timing_profile(&mut self, profile: TimingProfile) {
+        *self.sender.timing() = Timing::new(profile.clone());
+        self.timing = Timing::new(profile);
+    }
+
+    pub fn hold(&mut self, button: impl Into<Button>) -> Result<HoldGuard<'_>> {
+        let button = button.into();
+        self.press(button)?;
+        Ok(HoldGuard::new(self, button))
+    }
+
+    pub fn with_modifiers<M, T, F>(&mut self, modifiers: &[M], action: F) -> Result<T>
+    where
+        M: Into<Button> + Copy,
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let mut held = Vec::with_capacity(modifiers.len());
+        let mut result = Ok(());
+
+        for &modifier in modifiers {
+            let modifier = modifier.into();
+            result = self.press(modifier);
+            if result.is_err() {
+                break;
+            }
+            held.push(modifier);
+        }
+
+        let result = result.and_then(|_| action(self));
+
+        // release in reverse order even if the action failed
+        let mut release_result = Ok(());
+        for &modifier in held.iter().rev() {
+            if let Err(err) = self.release(modifier) {
+                release_result = Err(err);
+            }
+        }
+
+        let value = result?;
+        release_result?;
+        Ok(value)
+    }
+
+    #[inline]
+    pub fn is_pressed(&self, button: impl Into<Button>) -> bool {
+        self.pressed.contains(&button.into())
+    }
+
+    pub fn pressed_keys(&self) -> Vec<Key> {
+        self.pressed.iter().copied().collect()
+    }
+
+    pub fn release_all(&mut self) -> EmptyResult {
+        if self.pressed.is_empty() {
+            return Ok(());
+        }
+
+        self.write_built_batch(|device, batch| {
+            batch.extend(device.pressed.iter().rev().map(|key| (EV_KEY, key.code(), 0)));
+            batch.push(SYN_PARAMS);
+            Ok(())
+        })
+    }
+}
+
+impl Drop for VirtualDevice {
+    fn drop(&mut self) {
+        // don't leave modifiers stuck in the compositor after the device is gone
+        let _ = self.release_all();
+        self.end_services();
+
+        unsafe {
+            ui_dev_destroy(self.file.as_raw_fd());
+        }
+    }
+}
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::{ErrorKind, Read};
+    use std::os::fd::FromRawFd;
+
+    use super::*;
+    use crate::writer::EVENT_SIZE;
+
+    const PIPE_SIZE: usize = 4096;
+
+    // a device writing into an empty non-blocking pipe, returned with the read end.
+    // batches over one page are written partially, a page holds 170 whole events
+    pub(crate) fn pipe_device() -> (File, VirtualDevice) {
+        let mut fds = [0; 2];
+        let (reader, writer) = unsafe {
+            assert_eq!(libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC), 0);
+            assert_eq!(libc::fcntl(fds[1], libc::F_SETPIPE_SZ, PIPE_SIZE as libc::c_int), PIPE_SIZE as libc::c_int);
+            (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]))
+        };
+
+        let def = unsafe { mem::zeroed() };
+        let mut device = VirtualDevice::from_parts(writer, def, Duration::from_millis(1), 50);
+        device.set_write_timeout(Duration::ZERO);
+        (reader, device)
+    }
+
+    // every key, the pointer and the wheels are registered so handles accept whatever they send
+    pub(crate) fn handle_device() -> (File, VirtualDevice) {
+        let (reader, mut device) = pipe_device();
+        for key in 0..KEY_CNT {
+            device.capabilities.add_key(Key(key));
+        }
+        for axis in [RelAxis::X, RelAxis::Y].into_iter().chain(SCROLL_AXES) {
+            device.capabilities.add_rel(axis);
+        }
+        (reader, device)
+    }
+
+    // 160 moves followed by `count` presses, so the first ten presses fit in the pipe
+    pub(crate) fn presses(count: u16) -> Vec<EventParams> {
+        let moves = (0..160).map(|_| (EV_REL, REL_X, 1));
+        moves.chain((1..=count).map(|code| (EV_KEY, code, 1))).chain([SYN_PARAMS]).collect()
+    }
+
+    // everything the pipe holds right now, reading ends at EOF once the device is gone
+    pub(crate) fn drain(reader: &mut File) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match reader.read_to_end(&mut bytes) {
+            Ok(_) => bytes,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => bytes,
+            result => panic!("pipe read ended with {result:?}"),
+        }
+    }
+
+    pub(crate) fn read_events(reader: &mut File) -> Vec<EventParams> {
+        let bytes = drain(reader);
+        assert_eq!(bytes.len() % EVENT_SIZE, 0, "a torn event reached the reader");
+        bytes
+            .chunks_exact(EVENT_SIZE)
+            .map(|chunk| {
+                let event = unsafe { (chunk.as_ptr() as *const input_event).read_unaligned() };
+                (event.kind, event.code, event.value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn separate_definition_is_rejected_before_opening_uinput() {
+        match VirtualDevice::new(Duration::from_millis(1), 50, DeviceDefinitionType::Separate) {
+            Err(err) => assert!(err.to_string().contains("default_separate"), "{err}"),
+            Ok(_) => panic!("created a `Separate` device"),
+        }
+    }
+
+    #[test]
+    fn timed_out_press_tracks_only_committed_keys() {
+        let (reader, mut device) = pipe_device();
+
+        // the pipe only makes space for the rest of the torn eleventh press once it's read
+        let drain_later = thread::spawn(move || {
+            let mut reader = reader;
+            thread::sleep(Duration::from_millis(20));
+            let bytes = drain(&mut reader);
+            (reader, bytes)
+        });
+        let err = device.write_batch(&presses(20)).unwrap_err();
+        let err = err.downcast_ref::<WriteError>().unwrap();
+        assert!(matches!(err, WriteError::Timeout { committed: 171 }), "{err:?}");
+
+        let expected: Vec<Key> = (1..=11).map(Key).collect();
+        assert_eq!(device.pressed_keys(), expected);
+        assert!(!device.is_pressed(Key(12)));
+
+        // the moves and the eleven presses that went through
+        let (mut reader, bytes) = drain_later.join().unwrap();
+        assert_eq!(bytes.len() + drain(&mut reader).len(), 171 * EVENT_SIZE);
+
+        device.release_all().unwrap();
+        let mut releases: Vec<EventParams> = (1..=11).rev().map(|code| (EV_KEY, code, 0)).collect();
+        releases.push(SYN_PARAMS);
+        assert_eq!(read_events(&mut reader), releases);
+        assert!(device.pressed_keys().is_empty());
+    }
+
+    #[test]
+    fn whole_press_batch_tracks_every_key() {
+        let (mut reader, mut device) = pipe_device();
+
+        device.write_batch(&presses(5)).unwrap();
+        assert_eq!(device.pressed_keys().len(), 5);
+        assert_eq!(read_events(&mut reader), presses(5));
+
+        device.release_all().unwrap();
+        assert_eq!(read_events(&mut reader).len(), 6);
+    }
+
+    fn key_events(events: &[EventParams]) -> Vec<(u16, i32)> {
+        events.iter().filter(|event| event.0 == EV_KEY).map(|&(_, code, value)| (code, value)).collect()
+    }
+
+    #[test]
+    fn handle_clicks_dont_overlap() {
+        let (mut reader, mut device) = handle_device();
+        let handle = device.handle();
+
+        handle.click(KEY_L).unwrap();
+        handle.click(KEY_L).unwrap();
+        device.drain_channel().unwrap();
+
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_L, 1), (KEY_L, 0), (KEY_L, 1), (KEY_L, 0)]);
+    }
+
+    #[test]
+    fn click_after_type_text_waits_for_the_text() {
+        let (mut reader, mut device) = handle_device();
+        let handle = device.handle();
+
+        handle.type_text("ab").unwrap();
+        handle.click(KEY_C).unwrap();
+        device.drain_channel().unwrap();
+
+        assert_eq!(key_events(&read_events(&mut reader)), [
+            (KEY_A, 1), (KEY_A, 0), (KEY_B, 1), (KEY_B, 0), (KEY_C, 1), (KEY_C, 0),
+        ]);
+    }
+
+    #[test]
+    fn static_clicks_hold_for_the_device_timing_profile() {
+        let (mut reader, mut device) = handle_device();
+        device.set_timing_profile(TimingProfile { hold: Duration::from_secs(60), ..TimingProfile::default() });
+
+        VirtualDevice::send_click(KEY_A, &device.sender).unwrap();
+        VirtualDevice::send_double_click(KEY_B, &device.sender).unwrap();
+        // long past the default hold, but the release is due in a minute
+        sleep(Duration::from_millis(20));
+        device.write_events_from_channel().unwrap();
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_A, 1)]);
+    }
+
+    #[test]
+    fn immediate_frames_wait_for_a_running_sequence() {
+        let (mut reader, mut device) = handle_device();
+        let handle = device.handle();
+
+        handle.click_for(KEY_A, Duration::from_secs(60)).unwrap();
+        handle.press(KEY_B).unwrap();
+        // the timeline belongs to the device, handles with another policy wait as well
+        handle.with_backpressure(Backpressure::Unbounded).press(KEY_C).unwrap();
+        // nothing of the click is due yet but the press, so the other presses can't go out either
+        device.write_events_from_channel().unwrap();
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_A, 1)]);
+
+        device.drain_channel().unwrap();
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_A, 0), (KEY_B, 1), (KEY_C, 1)]);
+    }
+
+    #[test]
+    fn services_run_on_the_flush_thread_when_their_input_wakes_them() {
+        let (mut reader, mut device) = handle_device();
+        let mouse_keys = MouseKeys::new(MouseKeysSettings::default());
+        let input = mouse_keys.input();
+        let service = mouse_keys.spawn(Duration::from_millis(1), device.handle()).unwrap();
+
+        // registered and run once, then idle until the input changes
+        device.write_events_from_channel().unwrap();
+        assert!(service.is_running());
+        assert_eq!(device.services.len(), 1);
+        assert_eq!(device.services[0].1, None);
+
+        input.click(KEY_B).unwrap();
+        device.drain_channel().unwrap();
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_B, 1), (KEY_B, 0)]);
+
+        // a stopped flush thread takes its services along
+        assert!(!service.is_running());
+    }
+
+    #[test]
+    fn stopped_services_are_removed() {
+        let (_reader, mut device) = handle_device();
+        let service = spawn_jiggler(JigglerSettings::default(), device.handle()).unwrap();
+
+        device.write_events_from_channel().unwrap();
+        assert_eq!(device.services.len(), 1);
+        assert!(device.services[0].1.is_some());
+
+        service.stop();
+        device.write_events_from_channel().unwrap();
+        assert!(device.services.is_empty());
+    }
+
+    #[test]
+    fn keypad_5_clicks_through_the_mouse_keys_service() {
+        let (mut reader, mut device) = handle_device();
+        let mouse_keys = MouseKeys::new(MouseKeysSettings { button: Key::BTN_MIDDLE, ..MouseKeysSettings::default() });
+        let input = mouse_keys.input();
+        let _service = mouse_keys.spawn(Duration::from_millis(1), device.handle()).unwrap();
+
+        device.write_events_from_channel().unwrap();
+        input.handle_key(KEY_KP5, true);
+        input.handle_key(KEY_KP5, false);
+        device.drain_channel().unwrap();
+
+        assert_eq!(key_events(&read_events(&mut reader)), [(BTN_MIDDLE, 1), (BTN_MIDDLE, 0)]);
+    }
+
+    #[test]
+    fn jiggler_writes_its_frames_once_an_interval_has_passed() {
+        let jiggled = |mode| {
+            let (mut reader, mut device) = handle_device();
+            let settings = JigglerSettings { interval: Duration::from_millis(1), mode, return_after: Duration::ZERO };
+            let _service = spawn_jiggler(settings, device.handle()).unwrap();
+
+            device.write_events_from_channel().unwrap();
+            assert!(read_events(&mut reader).is_empty());
+            sleep(Duration::from_millis(2));
+            device.drain_channel().unwrap();
+            read_events(&mut reader)
+        };
+
+        assert_eq!(jiggled(JiggleMode::Move(2)), [(EV_REL, REL_X, 2), SYN_PARAMS, (EV_REL, REL_X, -2), SYN_PARAMS]);
+        assert_eq!(key_events(&jiggled(JigglerSettings::no_op_key().mode)), [(KEY_F24, 1), (KEY_F24, 0)]);
+    }
+
+    #[test]
+    fn pointer_controller_ticks_on_the_flush_thread_until_the_stick_is_released() {
+        let (mut reader, mut device) = handle_device();
+        let settings = PointerSettings { acceleration: 0.0, ..PointerSettings::default() };
+        let controller = PointerController::new(settings);
+        let input = controller.input();
+        input.set(1.0, 0.0);
+        let _service = controller.spawn(Duration::from_millis(1), device.handle()).unwrap();
+
+        device.write_events_from_channel().unwrap();
+        sleep(Duration::from_millis(10));
+        device.write_events_from_channel().unwrap();
+        let moved: i32 = read_events(&mut reader).iter().filter(|event| event.1 == REL_X).map(|event| event.2).sum();
+        assert!(moved > 0);
+
+        input.set(0.0, 0.0);
+        sleep(Duration::from_millis(2));
+        device.write_events_from_channel().unwrap();
+        assert_eq!(device.services[0].1, None);
+    }
+
+    #[test]
+    fn mouse_keys_motion_goes_through_the_coordinate_system() {
+        let up_moves = |coordinate_system| {
+            let (mut reader, mut device) = handle_device();
+            device.set_coordinate_system(coordinate_system);
+            let mouse_keys = MouseKeys::new(MouseKeysSettings { time_to_max: Duration::ZERO, ..MouseKeysSettings::default() });
+            let input = mouse_keys.input();
+            input.set_direction(Direction::Up, true);
+            let _service = mouse_keys.spawn(Duration::from_millis(1), device.handle()).unwrap();
+
+            device.write_events_from_channel().unwrap();
+            sleep(Duration::from_millis(10));
+            device.write_events_from_channel().unwrap();
+            read_events(&mut reader).iter().filter(|event| event.1 == REL_Y).map(|event| event.2).sum::<i32>()
+        };
+
+        assert!(up_moves(CoordinateSystem::Y_UP) < 0);
+        assert!(up_moves(CoordinateSystem::KERNEL) > 0);
+    }
+
+    fn scroll_device() -> (File, VirtualDevice) {
+        let (reader, mut device) = pipe_device();
+        for axis in SCROLL_AXES {
+            device.capabilities.add_rel(axis);
+        }
+        (reader, device)
+    }
+
+    fn wheel_events(events: &[EventParams]) -> usize {
+        events.iter().filter(|event| event.1 == REL_WHEEL_HI_RES).count()
+    }
+
+    #[test]
+    fn fling_guard_writes_the_fling_and_cancels_it_when_dropped() {
+        let (mut reader, mut device) = scroll_device();
+
+        {
+            let mut fling = device.fling_scroll(0.0, 1000.0, 5.0).unwrap();
+            sleep(Duration::from_millis(5));
+            assert!(fling.tick().unwrap());
+        }
+        assert!(!device.is_flinging());
+        assert_eq!(wheel_events(&read_events(&mut reader)), 1);
+
+        device.fling_scroll(0.0, 1000.0, 5.0).unwrap().detach();
+        assert!(device.is_flinging());
+    }
+
+    #[test]
+    fn only_written_scrolls_cancel_a_fling() {
+        let (_reader, mut device) = scroll_device();
+        device.fling_scroll(0.0, 1000.0, 5.0).unwrap().detach();
+
+        assert_eq!(device.buffered_scroll(0, 1), [(EV_REL, REL_HWHEEL_HI_RES, 0), (EV_REL, REL_WHEEL_HI_RES, 1), SYN_PARAMS]);
+        device.buffered_scroll_into(0, 1, &mut Vec::new());
+        assert!(device.is_flinging());
+
+        device.scroll(0, 1).unwrap();
+        assert!(!device.is_flinging());
+    }
+
+    #[test]
+    fn scheduled_scrolls_cancel_only_flings_started_before_them() {
+        let (_reader, mut device) = scroll_device();
+        let scroll = || ChannelEvent::At(Instant::now(), Frame::builder().scroll(0.0, 1.0).build());
+        let fling = || ChannelEvent::Fling(Fling::new(0.0, 1000.0, Decay::Exponential(5.0)).unwrap());
+
+        device.sender.send(scroll()).unwrap();
+        device.sender.send(fling()).unwrap();
+        device.write_events_from_channel().unwrap();
+        assert!(device.is_flinging());
+
+        device.sender.send(scroll()).unwrap();
+        device.write_events_from_channel().unwrap();
+        assert!(!device.is_flinging());
+    }
+
+    #[test]
+    fn handle_settings_stay_available_while_a_send_blocks() {
+        let (_reader, mut device) = handle_device();
+        let handle = device.handle();
+        for _ in 0..handle.channel_size() {
+            handle.press(KEY_A).unwrap();
+        }
+
+        let typing = {
+            let handle = handle.clone();
+            thread::spawn(move || handle.type_text("a").is_ok())
+        };
+        sleep(Duration::from_millis(10));
+
+        let (sender, profile) = bounded(1);
+        thread::spawn(move || sender.send(handle.timing_profile()).unwrap());
+        assert!(profile.recv_timeout(Duration::from_secs(1)).is_ok());
+
+        device.drain_channel().unwrap();
+        assert!(typing.join().unwrap());
+    }
+
+    fn press_frame(key: u16) -> Frame {
+        Frame::builder().press(Key(key)).build()
+    }
+
+    #[test]
+    fn scheduled_frames_are_written_in_deadline_order() {
+        let (mut reader, mut device) = pipe_device();
+        let now = Instant::now();
+
+        device.sender.send(ChannelEvent::At(now + Duration::from_millis(20), press_frame(KEY_B))).unwrap();
+        device.sender.send(ChannelEvent::At(now + Duration::from_millis(10), press_frame(KEY_A))).unwrap();
+        device.drain_channel().unwrap();
+
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_A, 1), (KEY_B, 1)]);
+    }
+
+    #[test]
+    fn frames_with_the_same_deadline_keep_their_sending_order() {
+        let (mut reader, mut device) = pipe_device();
+        let at = Instant::now() + Duration::from_millis(10);
+
+        for key in [KEY_C, KEY_A, KEY_B] {
+            device.sender.send(ChannelEvent::At(at, press_frame(key))).unwrap();
+        }
+        device.drain_channel().unwrap();
+
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_C, 1), (KEY_A, 1), (KEY_B, 1)]);
+    }
+
+    #[test]
+    fn cancelled_frames_are_never_written() {
+        let (mut reader, mut device) = handle_device();
+        let handle = device.handle();
+
+        let cancelled = handle.send_after(Duration::from_secs(60), press_frame(KEY_A)).unwrap();
+        handle.send_after(Duration::from_secs(60), press_frame(KEY_B)).unwrap();
+        handle.cancel(cancelled).unwrap();
+        device.drain_channel().unwrap();
+
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_B, 1)]);
+    }
+
+    #[test]
+    fn future_frames_wait_for_their_deadline_until_the_channel_is_drained() {
+        let (mut reader, mut device) = handle_device();
+        let handle = device.handle();
+
+        handle.send_after(Duration::from_secs(60), press_frame(KEY_A)).unwrap();
+        device.write_events_from_channel().unwrap();
+        assert!(read_events(&mut reader).is_empty());
+        assert_eq!(device.scheduled.len(), 1);
+
+        device.drain_channel().unwrap();
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_A, 1)]);
+        assert!(device.scheduled.is_empty());
+    }
+
+    #[test]
+    fn stopping_the_flush_thread_drains_the_channel_and_disconnects_senders() {
+        let (mut reader, device) = handle_device();
+        let handle = device.handle();
+        handle.send_after(Duration::from_secs(60), press_frame(KEY_A)).unwrap();
+        handle.press(KEY_B).unwrap();
+
+        let flush = device.flush_channel_every_interval();
+        flush.stop().unwrap();
+
+        // the scheduled press goes out right away, then the dropped device releases both keys
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_B, 1), (KEY_A, 1), (KEY_B, 0), (KEY_A, 0)]);
+        assert!(handle.press(KEY_C).is_err());
+    }
+
+    #[test]
+    fn failed_write_stops_the_flush_thread_and_is_reported() {
+        let (reader, device) = handle_device();
+        let handle = device.handle();
+        drop(reader);
+
+        let (sender, reported) = bounded(1);
+        let flush = device.flush_channel_every_interval_with(move |err| sender.send(err.clone()).unwrap());
+        handle.press(KEY_A).unwrap();
+
+        let reported = reported.recv_timeout(Duration::from_secs(1)).unwrap();
+        while flush.is_running() {
+            sleep(Duration::from_millis(1));
+        }
+        assert_eq!(reported.raw_os_error(), Some(libc::EPIPE));
+        assert_eq!(flush.error(), Some(reported.clone()));
+        assert!(handle.press(KEY_B).is_err());
+        assert_eq!(flush.stop(), Err(reported));
+    }
+
+    #[test]
+    fn blocking_flush_coalesces_a_burst_within_the_window() {
+        let (mut reader, mut device) = handle_device();
+        device.set_coalescing(true);
+        let handle = device.handle();
+        let flush = device.flush_channel_blocking(Duration::from_millis(50));
+
+        handle.move_mouse(1, 0).unwrap();
+        sleep(Duration::from_millis(5));
+        handle.move_mouse(2, 0).unwrap();
+        sleep(Duration::from_millis(100));
+
+        assert_eq!(read_events(&mut reader), [(EV_REL, REL_X, 3), SYN_PARAMS]);
+        flush.stop().unwrap();
+    }
+
+    #[test]
+    fn blocking_flush_wakes_up_for_scheduled_frames() {
+        let (mut reader, device) = handle_device();
+        let handle = device.handle();
+        let flush = device.flush_channel_blocking(Duration::from_millis(1));
+
+        handle.send_after(Duration::from_millis(30), press_frame(KEY_A)).unwrap();
+        sleep(Duration::from_millis(10));
+        assert!(read_events(&mut reader).is_empty());
+
+        sleep(Duration::from_millis(100));
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_A, 1)]);
+        flush.stop().unwrap();
+    }
+
+    #[test]
+    fn keystrokes_start_right_away_and_pause_after_words() {
+        let profile = TimingProfile {
+            word_pause: Duration::from_millis(100),
+            ..TimingProfile::fixed(Duration::from_millis(10), Duration::from_millis(20))
+        };
+        let keystrokes = plan_keystrokes("a B", &mut Timing::new(profile)).unwrap();
+
+        let delays: Vec<_> = keystrokes.iter().map(|keystroke| keystroke.delay.as_millis()).collect();
+        assert_eq!(delays, [0, 20, 120]);
+        assert!(keystrokes.iter().all(|keystroke| keystroke.hold == Duration::from_millis(10)));
+        assert_eq!(keystrokes.iter().map(|keystroke| (keystroke.key, keystroke.shift)).collect::<Vec<_>>(), [
+            (Key(KEY_A), false), (Key(KEY_SPACE), false), (Key(KEY_B), true),
+        ]);
+    }
+
+    #[test]
+    fn unsupported_characters_fail_before_anything_is_planned() {
+        assert!(plan_keystrokes("ab\u{20ac}", &mut Timing::default()).is_err());
+
+        let (mut reader, mut device) = pipe_device();
+        assert!(device.handle().type_text("ab\u{20ac}").is_err());
+        device.drain_channel().unwrap();
+        assert!(read_events(&mut reader).is_empty());
+    }
+
+    #[test]
+    fn accumulate_carries_sub_pixel_remainders_across_calls() {
+        let mut remainders = [0.0; REL_CNT as usize];
+
+        let moved: Vec<_> = (0..10).map(|_| accumulate(&mut remainders, RelAxis::X, 0.25)).collect();
+        assert_eq!(moved, [0, 0, 0, 1, 0, 0, 0, 1, 0, 0]);
+        assert_eq!(remainders[REL_X as usize], 0.5);
+
+        // each axis keeps its own remainder, negative motion truncates toward zero
+        assert_eq!(accumulate(&mut remainders, RelAxis::Y, -0.6), 0);
+        assert_eq!(accumulate(&mut remainders, RelAxis::Y, -0.6), -1);
+        assert!((remainders[REL_Y as usize] + 0.2).abs() < 1e-9);
+        assert_eq!(remainders[REL_X as usize], 0.5);
+    }
+
+    #[test]
+    fn fractional_channel_moves_add_up_on_the_device() {
+        let (mut reader, mut device) = handle_device();
+        let handle = device.handle();
+
+        for _ in 0..4 {
+            handle.move_mouse_f(0.5, 0.0).unwrap();
+        }
+        device.drain_channel().unwrap();
+
+        let moved: i32 = read_events(&mut reader).iter().filter(|event| event.1 == REL_X).map(|event| event.2).sum();
+        assert_eq!(moved, 2);
+    }
+
+    fn keyboard_device() -> (File, VirtualDevice) {
+        let (reader, mut device) = pipe_device();
+        for key in [KEY_A, KEY_B, KEY_LEFTCTRL, KEY_LEFTSHIFT] {
+            device.capabilities.add_key(Key(key));
+        }
+        (reader, device)
+    }
+
+    #[test]
+    fn services_refuse_devices_that_cant_write_their_frames() {
+        let (_reader, keyboard) = keyboard_device();
+        assert!(spawn_jiggler(JigglerSettings::default(), keyboard.handle()).is_err());
+        assert!(PointerController::new(PointerSettings::default()).spawn(Duration::from_millis(1), keyboard.handle()).is_err());
+        assert!(MouseKeys::new(MouseKeysSettings::default()).spawn(Duration::from_millis(1), keyboard.handle()).is_err());
+
+        // a mouse without F24 or the middle button
+        let (_reader, mut mouse) = pipe_device();
+        mouse.capabilities.add_rel(RelAxis::X);
+        mouse.capabilities.add_rel(RelAxis::Y);
+        mouse.capabilities.add_key(Key::BTN_LEFT);
+        assert!(spawn_jiggler(JigglerSettings::no_op_key(), mouse.handle()).is_err());
+        let middle = MouseKeysSettings { button: Key::BTN_MIDDLE, ..MouseKeysSettings::default() };
+        assert!(MouseKeys::new(middle).spawn(Duration::from_millis(1), mouse.handle()).is_err());
+
+        // once spawned, clicks of buttons the device doesn't have fail right away
+        let mouse_keys = MouseKeys::new(MouseKeysSettings::default());
+        let input = mouse_keys.input();
+        let _service = mouse_keys.spawn(Duration::from_millis(1), mouse.handle()).unwrap();
+        assert!(input.click(Key::BTN_RIGHT).is_err());
+        input.click(Key::BTN_LEFT).unwrap();
+    }
+
+    #[test]
+    fn unregistered_codes_are_rejected_before_writing() {
+        let (mut reader, mut device) = keyboard_device();
+
+        assert!(device.press(KEY_C).is_err());
+        assert!(device.move_mouse(1, 0).is_err());
+        assert!(device.emit(&[InputEvent::press(KEY_A), InputEvent::Other(EventType::SW, SW_LID, 1)]).is_err());
+        assert!(read_events(&mut reader).is_empty());
+        assert!(device.pressed_keys().is_empty());
+    }
+
+    #[test]
+    fn handles_reject_unregistered_codes_before_sending() {
+        let (mut reader, mut device) = keyboard_device();
+        let handle = device.handle();
+
+        assert!(handle.press(KEY_X).is_err());
+        assert!(handle.click(BTN_LEFT).is_err());
+        assert!(handle.move_mouse(1, 0).is_err());
+        assert!(handle.scroll(0, 1).is_err());
+        assert!(handle.send_after(Duration::ZERO, Frame::builder().press(KEY_A).press(KEY_C).build()).is_err());
+        // "ac" has a registered first key, but nothing of the text is sent
+        assert!(handle.type_text("ac").is_err());
+        assert!(handle.emit(&[InputEvent::Other(EventType::SW, SW_LID, 1)]).is_err());
+
+        handle.click(KEY_A).unwrap();
+        device.drain_channel().unwrap();
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_A, 1), (KEY_A, 0)]);
+    }
+
+    #[test]
+    fn release_all_releases_what_is_held() {
+        let (mut reader, mut device) = keyboard_device();
+        device.press(KEY_B).unwrap();
+        device.press(KEY_A).unwrap();
+        assert_eq!(device.pressed_keys(), [Key(KEY_A), Key(KEY_B)]);
+
+        device.release_all().unwrap();
+        assert!(device.pressed_keys().is_empty());
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_B, 1), (KEY_A, 1), (KEY_B, 0), (KEY_A, 0)]);
+
+        // nothing left to release
+        device.release_all().unwrap();
+        assert!(read_events(&mut reader).is_empty());
+    }
+
+    #[test]
+    fn held_keys_are_released_when_a_panic_drops_the_device() {
+        let (mut reader, mut device) = keyboard_device();
+        device.press(KEY_LEFTCTRL).unwrap();
+        device.press(KEY_A).unwrap();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+            let _device = device;
+            panic!("between press and release");
+        }));
+        assert!(result.is_err());
+
+        assert_eq!(key_events(&read_events(&mut reader)), [
+            (KEY_LEFTCTRL, 1), (KEY_A, 1), (KEY_A, 0), (KEY_LEFTCTRL, 0),
+        ]);
+    }
+
+    #[test]
+    fn hold_guard_releases_on_drop_and_on_panic() {
+        let (mut reader, mut device) = keyboard_device();
+
+        {
+            let mut guard = device.hold(KEY_A).unwrap();
+            guard.click(KEY_B).unwrap();
+        }
+        // released through the guard, dropping it doesn't release again
+        device.hold(KEY_B).unwrap().release(KEY_B).unwrap();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = device.hold(KEY_LEFTCTRL).unwrap();
+            panic!("while holding");
+        }));
+        assert!(result.is_err());
+
+        assert_eq!(key_events(&read_events(&mut reader)), [
+            (KEY_A, 1), (KEY_B, 1), (KEY_B, 0), (KEY_A, 0),
+            (KEY_B, 1), (KEY_B, 0),
+            (KEY_LEFTCTRL, 1), (KEY_LEFTCTRL, 0),
+        ]);
+        assert!(device.pressed_keys().is_empty());
+    }
+
+    #[test]
+    fn modifiers_are_released_in_reverse_order_when_the_action_fails() {
+        let (mut reader, mut device) = keyboard_device();
+
+        let result: Result<()> = device.with_modifiers(&[KEY_LEFTCTRL, KEY_LEFTSHIFT], |device| {
+            device.click(KEY_A)?;
+            Err(Box::from("action failed"))
+        });
+        assert!(result.is_err());
+        assert_eq!(key_events(&read_events(&mut reader)), [
+            (KEY_LEFTCTRL, 1), (KEY_LEFTSHIFT, 1), (KEY_A, 1), (KEY_A, 0), (KEY_LEFTSHIFT, 0), (KEY_LEFTCTRL, 0),
+        ]);
+
+        // a modifier that can't be pressed skips the action and releases the ones before it
+        let result = device.with_modifiers(&[KEY_LEFTCTRL, KEY_RIGHTALT], |device| device.click(KEY_A));
+        assert!(result.is_err());
+        assert_eq!(key_events(&read_events(&mut reader)), [(KEY_LEFTCTRL, 1), (KEY_LEFTCTRL, 0)]);
+    }
+
+    #[test]
+    fn handle_guards_and_modifiers_release_through_the_channel() {
+        let (mut reader, mut device) = keyboard_device();
+        let handle = device.handle();
+
+        drop(handle.hold(KEY_A).unwrap());
+        let result: Result<()> = handle.with_modifiers(&[KEY_LEFTCTRL, KEY_LEFTSHIFT], |_| Err(Box::from("action failed")));
+        assert!(result.is_err());
+        device.drain_channel().unwrap();
+
+        assert_eq!(key_events(&read_events(&mut reader)), [
+            (KEY_A, 1), (KEY_A, 0), (KEY_LEFTCTRL, 1), (KEY_LEFTSHIFT, 1), (KEY_LEFTSHIFT, 0), (KEY_LEFTCTRL, 0),
+        ]);
+    }
+
+    fn tablet_device(max_x: Coord, max_y: Coord) -> (File, VirtualDevice) {
+        let (reader, mut device) = pipe_device();
+        for (axis, max) in [(AbsAxis::X, max_x), (AbsAxis::Y, max_y)] {
+            device.def.absmax[axis.code() as usize] = max;
+            device.capabilities.add_abs(axis);
+        }
+        (reader, device)
+    }
+
+    fn abs_moves(events: &[EventParams]) -> Vec<(Coord, Coord)> {
+        events.chunks_exact(3).map(|frame| {
+            assert_eq!(frame[2], SYN_PARAMS);
+            assert_eq!((frame[0].1, frame[1].1), (ABS_X, ABS_Y));
+            (frame[0].2, frame[1].2)
+        }).collect()
+    }
+
+    #[test]
+    fn moves_to_monitors_through_the_screen_geometry() {
+        let (mut reader, mut device) = tablet_device(65535, 65535);
+        assert!(device.move_to_monitor(0, 0.0, 0.0).is_err());
+
+        device.set_screen_geometry(Some(ScreenGeometry::new(vec![
+            Monitor::new(0, 0, 2880, 1800).with_scale(1.5),
+            Monitor::new(-1920, -200, 1920, 1080),
+        ]).unwrap()));
+        assert!(device.move_to_monitor(2, 0.0, 0.0).is_err());
+
+        device.move_to_monitor_percent(1, 0.0, 0.0).unwrap();
+        device.move_to_monitor_percent(0, 100.0, 100.0).unwrap();
+        // the last logical point of the panel scaled by 1.5 starts a pixel and a half before its edge
+        device.move_to_monitor(0, 1919.0, 1199.0).unwrap();
+        device.move_to_percent(50.0, 100.0).unwrap();
+
+        assert_eq!(abs_moves(&read_events(&mut reader)), [
+            (0, 0), (65535, 65535), (65528, 65519), (32768, 65535),
+        ]);
+    }
+
+    #[test]
+    fn single_axis_moves_write_only_the_axis_that_moves() {
+        let (mut reader, mut device) = pipe_device();
+        device.capabilities.add_rel(RelAxis::X);
+        device.capabilities.add_rel(RelAxis::Y);
+
+        device.move_mouse_x(3).unwrap();
+        device.move_mouse_raw_y(2).unwrap();
+        device.move_mouse(0, 0).unwrap();
+        device.move_mouse_raw(0, 0).unwrap();
+        assert_eq!(read_events(&mut reader), [(EV_REL, REL_X, 3), SYN_PARAMS, (EV_REL, REL_Y, -2)]);
+
+        // the axis is picked after the coordinate system has been applied
+        device.set_coordinate_system(CoordinateSystem::KERNEL.with_rotation(Rotation::Clockwise90));
+        device.move_mouse_x(3).unwrap();
+        device.move_mouse_raw_y(2).unwrap();
+        assert_eq!(read_events(&mut reader), [(EV_REL, REL_Y, 3), SYN_PARAMS, (EV_REL, REL_X, -2)]);
+    }
+
+    #[test]
+    fn buffered_moves_encode_as_given_and_into_goes_through_the_coordinate_system() {
+        let (_reader, mut device) = pipe_device();
+        device.set_coordinate_system(CoordinateSystem::KERNEL.with_rotation(Rotation::Clockwise90));
+
+        assert_eq!(device.buffered_move_mouse_x(4), [(EV_REL, REL_X, 4), SYN_PARAMS]);
+        assert_eq!(device.buffered_move_mouse_y(4), [(EV_REL, REL_Y, -4), SYN_PARAMS]);
+        assert_eq!(device.buffered_move_mouse(0, 0), [(EV_REL, REL_X, 0), (EV_REL, REL_Y, 0), SYN_PARAMS]);
+        assert_eq!(device.buffered_gradual_move_mouse(2, 0), [(EV_REL, REL_X, 1), SYN_PARAMS, (EV_REL, REL_X, 1), SYN_PARAMS]);
+
+        let mut batch = Vec::new();
+        device.buffered_move_mouse_into(0, 0, &mut batch);
+        assert_eq!(batch, []);
+        device.buffered_gradual_move_mouse_into(2, 0, &mut batch);
+        assert_eq!(batch, [(EV_REL, REL_Y, 1), SYN_PARAMS, (EV_REL, REL_Y, 1), SYN_PARAMS]);
+    }
+
+    #[test]
+    fn scrolls_leave_out_wheels_that_dont_move() {
+        let (mut reader, mut device) = scroll_device();
+
+        device.scroll(0, 0).unwrap();
+        device.scroll_raw(0, 0).unwrap();
+        assert!(read_events(&mut reader).is_empty());
+
+        device.scroll_x(2).unwrap();
+        device.scroll_raw_y(-1).unwrap();
+        assert_eq!(read_events(&mut reader), [(EV_REL, REL_HWHEEL_HI_RES, 2), SYN_PARAMS, (EV_REL, REL_WHEEL_HI_RES, -1)]);
+
+        let mut batch = Vec::new();
+        device.buffered_scroll_into(0, 0, &mut batch);
+        assert_eq!(batch, []);
+        device.buffered_scroll_into(-3, 0, &mut batch);
+        assert_eq!(batch, [(EV_REL, REL_HWHEEL_HI_RES, -3), SYN_PARAMS]);
+        // with the unit scrolled down before, a whole notch goes out next to the hi-res event
+        batch.clear();
+        device.buffered_scroll_into(0, 121, &mut batch);
+        assert_eq!(batch, [(EV_REL, REL_WHEEL_HI_RES, 121), (EV_REL, REL_WHEEL_NOTCH, 1), SYN_PARAMS]);
+
+        // the `Vec` helpers keep every wheel and send no notches
+        assert_eq!(device.buffered_scroll(0, 0), [(EV_REL, REL_HWHEEL_HI_RES, 0), (EV_REL, REL_WHEEL_HI_RES, 0), SYN_PARAMS]);
+        assert_eq!(device.buffered_scroll_y(121), [(EV_REL, REL_WHEEL_HI_RES, 121), SYN_PARAMS]);
+    }
+}
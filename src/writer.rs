@@ -0,0 +1,403 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::slice;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use crate::virtual_device::FIXED_TIME;
+use crate::{EventParams, input_event};
+
+pub(crate) const EVENT_SIZE: usize = size_of::<input_event>();
+pub const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+// poll can report the fd as writable while the write still fails with EAGAIN
+const MIN_BACKOFF: Duration = Duration::from_micros(50);
+const MAX_BACKOFF: Duration = Duration::from_millis(5);
+
+// `committed` is how many whole events of the batch reached the fd before the failure.
+// after a `Timeout` nothing else of the batch did, after `Io` the fd may hold part of the next event
+#[derive(Debug)]
+pub enum WriteError {
+    // the fd stayed full for the whole write timeout
+    Timeout { committed: usize },
+    Io { committed: usize, error: io::Error },
+}
+
+impl WriteError {
+    #[inline]
+    pub fn committed(&self) -> usize {
+        match self {
+            WriteError::Timeout { committed } | WriteError::Io { committed, .. } => *committed,
+        }
+    }
+
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            WriteError::Timeout { .. } => Some(libc::EAGAIN),
+            WriteError::Io { error, .. } => error.raw_os_error(),
+        }
+    }
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Timeout { committed } => {
+                write!(f, "Device stayed busy, only {committed} events were written")
+            }
+            WriteError::Io { committed, error } => {
+                write!(f, "{error}, only {committed} events were written")
+            }
+        }
+    }
+}
+
+impl Error for WriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WriteError::Timeout { .. } => None,
+            WriteError::Io { error, .. } => Some(error),
+        }
+    }
+}
+
+// `input_event`s of the batch being written, the capacity is kept between writes
+// so once it fits the largest batch no write allocates
+#[derive(Default)]
+pub(crate) struct EventBuffer {
+    events: Vec<input_event>,
+}
+
+impl EventBuffer {
+    // replaces the content with `batch` and returns it ready for a single write
+    pub(crate) fn encode(&mut self, batch: &[EventParams]) -> &[u8] {
+        self.events.clear();
+        self.events.extend(batch.iter().map(|&(kind, code, value)| input_event {
+            time: FIXED_TIME,
+            kind,
+            code,
+            value,
+        }));
+
+        self.bytes()
+    }
+
+    // the encoded batch as it is written
+    #[inline]
+    pub(crate) fn bytes(&self) -> &[u8] {
+        // `input_event` is repr(C) without padding
+        unsafe { slice::from_raw_parts(self.events.as_ptr() as *const u8, self.events.len() * EVENT_SIZE) }
+    }
+
+    #[cfg(feature = "async")]
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    #[cfg(feature = "async")]
+    #[inline]
+    pub(crate) fn event(&self, idx: usize) -> EventParams {
+        let event = &self.events[idx];
+        (event.kind, event.code, event.value)
+    }
+}
+
+// writes `bytes` from `*written` on until all of them are written or the fd is full,
+// `Ok(false)` means EAGAIN and a later call continues where this one stopped
+pub(crate) fn write_some(file: &File, bytes: &[u8], written: &mut usize) -> io::Result<bool> {
+    while *written < bytes.len() {
+        match (&*file).write(&bytes[*written..]) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(count) => *written += count,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(true)
+}
+
+// where a write that ran out of time stops: right away at an event boundary, otherwise
+// at the end of the torn event, since the reader can't resync on a misaligned stream
+#[inline]
+pub(crate) fn overdue_end(written: usize) -> Option<usize> {
+    (!written.is_multiple_of(EVENT_SIZE)).then(|| written.next_multiple_of(EVENT_SIZE))
+}
+
+// writes all of `bytes` to a non-blocking fd: EAGAIN waits in poll with a backoff
+// and a partial write continues where it stopped. once `timeout` runs out only the event
+// in flight is finished, so a `Timeout` always leaves the fd at an event boundary
+#[inline]
+pub(crate) fn write_whole(file: &File, bytes: &[u8], timeout: Duration) -> Result<(), WriteError> {
+    write_rest(file, bytes, &mut 0, timeout)
+}
+
+// `write_whole` for a batch of which `*written` bytes already went out
+pub(crate) fn write_rest(file: &File, bytes: &[u8], written: &mut usize, timeout: Duration) -> Result<(), WriteError> {
+    let deadline = Instant::now() + timeout;
+    let mut end = bytes.len();
+    let mut backoff = Duration::ZERO;
+
+    loop {
+        match write_some(file, &bytes[..end], written) {
+            Ok(true) if end == bytes.len() => return Ok(()),
+            Ok(true) => return Err(WriteError::Timeout { committed: *written / EVENT_SIZE }),
+            Ok(false) => {}
+            Err(error) => return Err(WriteError::Io { committed: *written / EVENT_SIZE, error }),
+        }
+        let committed = *written / EVENT_SIZE;
+
+        if end < bytes.len() {
+            // overdue, the torn event is waited for without a deadline
+            wait_writable(file, None).map_err(|error| WriteError::Io { committed, error })?;
+            continue;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            match overdue_end(*written) {
+                Some(torn_end) => end = torn_end,
+                None => return Err(WriteError::Timeout { committed }),
+            }
+            continue;
+        }
+        if !backoff.is_zero() {
+            sleep(backoff.min(remaining));
+        }
+        backoff = (backoff * 2).clamp(MIN_BACKOFF, MAX_BACKOFF);
+
+        wait_writable(file, Some(deadline)).map_err(|error| WriteError::Io { committed, error })?;
+    }
+}
+
+// `None` waits as long as it takes
+fn wait_writable(file: &File, deadline: Option<Instant>) -> io::Result<()> {
+    let mut poll_fd = libc::pollfd {
+        fd: file.as_raw_fd(),
+        events: libc::POLLOUT,
+        revents: 0,
+    };
+    // rounded up so a sub-millisecond remainder still waits instead of spinning
+    let timeout_ms = match deadline {
+        Some(deadline) => {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            remaining.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32
+        }
+        None => -1,
+    };
+
+    match unsafe { libc::poll(&mut poll_fd, 1, timeout_ms) } {
+        -1 => match io::Error::last_os_error() {
+            error if error.kind() == io::ErrorKind::Interrupted => Ok(()),
+            error => Err(error),
+        },
+        // timed out or ready, the next write tells which
+        _ => Ok(()),
+    }
+}
+
+// the frame writer of `VirtualDevice` on its own. not part of the API,
+// it's public for the allocation test and the benchmarks, which need their own binaries
+#[doc(hidden)]
+pub struct EventWriter {
+    file: File,
+    timeout: Duration,
+    buffer: EventBuffer,
+}
+
+impl EventWriter {
+    pub fn new(fd: impl Into<OwnedFd>) -> Self {
+        Self {
+            file: File::from(fd.into()),
+            timeout: DEFAULT_WRITE_TIMEOUT,
+            buffer: EventBuffer::default(),
+        }
+    }
+
+    // how long a full fd is waited for, zero fails right away
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    #[inline]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn write_events(&mut self, batch: &[EventParams]) -> Result<(), WriteError> {
+        write_whole(&self.file, self.buffer.encode(batch), self.timeout)
+    }
+}
+
+impl fmt::Debug for EventWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventWriter")
+            .field("file", &self.file)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // a non-blocking pipe stands in for /dev/uinput, its small buffer forces EAGAIN and partial writes
+    use std::io::Read;
+    use std::os::fd::FromRawFd;
+    use std::thread;
+
+    use super::*;
+    use crate::key_codes::*;
+
+    const PIPE_SIZE: usize = 4096;
+
+    fn pipe() -> (File, OwnedFd) {
+        let mut fds = [0; 2];
+        unsafe {
+            assert_eq!(libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC), 0);
+            assert_eq!(libc::fcntl(fds[1], libc::F_SETPIPE_SZ, PIPE_SIZE as libc::c_int), PIPE_SIZE as libc::c_int);
+            (File::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))
+        }
+    }
+
+    fn events(count: usize) -> Vec<EventParams> {
+        (0..count)
+            .map(|idx| match idx % 3 {
+                0 => (EV_REL, REL_X, idx as i32),
+                1 => (EV_REL, REL_Y, -(idx as i32)),
+                _ => (EV_SYN, SYN_REPORT, 0),
+            })
+            .collect()
+    }
+
+    fn decode(bytes: &[u8]) -> Vec<EventParams> {
+        assert_eq!(bytes.len() % EVENT_SIZE, 0, "a torn event reached the reader");
+        bytes
+            .chunks_exact(EVENT_SIZE)
+            .map(|chunk| {
+                let event = unsafe { (chunk.as_ptr() as *const input_event).read_unaligned() };
+                (event.kind, event.code, event.value)
+            })
+            .collect()
+    }
+
+    fn read_available(reader: &mut File) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut chunk = [0; PIPE_SIZE];
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => return bytes,
+                Ok(count) => bytes.extend_from_slice(&chunk[..count]),
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => return bytes,
+                Err(err) => panic!("{err}"),
+            }
+        }
+    }
+
+    #[test]
+    fn writes_whole_batch_in_order() {
+        let (mut reader, writer) = pipe();
+        let mut writer = EventWriter::new(writer);
+        let batch = events(100);
+
+        writer.write_events(&batch).unwrap();
+
+        assert_eq!(decode(&read_available(&mut reader)), batch);
+    }
+
+    #[test]
+    fn full_pipe_times_out_without_committing() {
+        let (mut reader, writer) = pipe();
+        let mut filler = File::from(writer.try_clone().unwrap());
+        filler.write_all(&[0; PIPE_SIZE]).unwrap();
+        let mut writer = EventWriter::new(writer).with_timeout(Duration::from_millis(20));
+
+        let started = Instant::now();
+        let err = writer.write_events(&events(2)).unwrap_err();
+
+        assert!(matches!(err, WriteError::Timeout { committed: 0 }), "{err:?}");
+        assert_eq!(err.raw_os_error(), Some(libc::EAGAIN));
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        assert_eq!(read_available(&mut reader).len(), PIPE_SIZE);
+    }
+
+    #[test]
+    fn zero_timeout_fails_right_away() {
+        let (_reader, writer) = pipe();
+        let mut filler = File::from(writer.try_clone().unwrap());
+        filler.write_all(&[0; PIPE_SIZE]).unwrap();
+        let mut writer = EventWriter::new(writer).with_timeout(Duration::ZERO);
+
+        let started = Instant::now();
+        let err = writer.write_events(&events(1)).unwrap_err();
+
+        assert!(matches!(err, WriteError::Timeout { committed: 0 }), "{err:?}");
+        assert!(started.elapsed() < Duration::from_millis(10));
+    }
+
+    #[test]
+    fn timeout_finishes_the_torn_event() {
+        let (mut reader, writer) = pipe();
+        let mut writer = EventWriter::new(writer).with_timeout(Duration::from_millis(20));
+        let batch = events(400);
+
+        // the pipe takes 4096 bytes, which ends in the middle of event 171.
+        // its rest only fits once the reader makes space, long after the timeout
+        let drain = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            let bytes = read_available(&mut reader);
+            (reader, bytes)
+        });
+        let err = writer.write_events(&batch).unwrap_err();
+
+        let committed = PIPE_SIZE.div_ceil(EVENT_SIZE);
+        assert!(matches!(err, WriteError::Timeout { .. }), "{err:?}");
+        assert_eq!(err.committed(), committed);
+
+        let (mut reader, mut bytes) = drain.join().unwrap();
+        assert_eq!(bytes.len(), PIPE_SIZE);
+        bytes.extend(read_available(&mut reader));
+        assert_eq!(decode(&bytes), batch[..committed]);
+    }
+
+    #[test]
+    fn resumes_partial_writes_until_the_batch_is_complete() {
+        let (mut reader, writer) = pipe();
+        let mut writer = EventWriter::new(writer).with_timeout(Duration::from_secs(5));
+        let batch = events(5000);
+        let expected_len = batch.len() * EVENT_SIZE;
+
+        let drain = thread::spawn(move || {
+            let mut bytes = Vec::new();
+            let mut chunk = [0; 1000];
+            while bytes.len() < expected_len {
+                match reader.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(count) => bytes.extend_from_slice(&chunk[..count]),
+                    Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => thread::sleep(Duration::from_micros(200)),
+                    Err(err) => panic!("{err}"),
+                }
+            }
+            bytes
+        });
+
+        writer.write_events(&batch).unwrap();
+
+        assert_eq!(decode(&drain.join().unwrap()), batch);
+    }
+
+    #[test]
+    fn closed_reader_is_an_io_error() {
+        let (reader, writer) = pipe();
+        drop(reader);
+        let mut writer = EventWriter::new(writer);
+
+        let err = writer.write_events(&events(3)).unwrap_err();
+
+        assert!(matches!(err, WriteError::Io { committed: 0, .. }), "{err:?}");
+        assert_eq!(err.raw_os_error(), Some(libc::EPIPE));
+    }
+}
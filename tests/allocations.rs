@@ -0,0 +1,123 @@
+// the write path must not allocate once its buffers have grown. the counting allocator
+// replaces the global one for this test binary only, so it lives apart from the unit tests.
+// the uinput case needs access to /dev/uinput and is skipped without it, like in benches/write_path.rs
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fs::File;
+use std::hint::black_box;
+use std::io::Read;
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::path::Path;
+
+use mouse_keyboard_input::key_codes::*;
+use mouse_keyboard_input::*;
+
+const PIPE_SIZE: usize = 4096;
+
+// counts the allocations of the current thread, so tests running in parallel don't interfere
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations_in(mut f: impl FnMut()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+fn pipe() -> (File, OwnedFd) {
+    let mut fds = [0; 2];
+    unsafe {
+        assert_eq!(libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC), 0);
+        assert_eq!(libc::fcntl(fds[1], libc::F_SETPIPE_SZ, PIPE_SIZE as libc::c_int), PIPE_SIZE as libc::c_int);
+        (File::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))
+    }
+}
+
+#[test]
+fn counter_sees_allocations() {
+    assert_eq!(allocations_in(|| drop(black_box(Vec::<EventParams>::with_capacity(3)))), 1);
+}
+
+#[test]
+fn event_writer_doesnt_allocate_once_warmed_up() {
+    let (mut reader, writer) = pipe();
+    let mut writer = EventWriter::new(writer);
+    let mut batch = Vec::new();
+    let mut pipe = [0; PIPE_SIZE];
+
+    let mut frame = |writer: &mut EventWriter| {
+        batch.clear();
+        for value in 1..=20 {
+            batch.extend([(EV_REL, REL_X, value), (EV_REL, REL_Y, -value), (EV_SYN, SYN_REPORT, 0)]);
+        }
+        writer.write_events(&batch).unwrap();
+        writer.write_events(&[(EV_REL, REL_X, 1), (EV_SYN, SYN_REPORT, 0)]).unwrap();
+        // the pipe is emptied into a buffer that already exists
+        while reader.read(&mut pipe).is_ok_and(|count| count > 0) {}
+    };
+
+    frame(&mut writer);
+    assert_eq!(allocations_in(|| (0..100).for_each(|_| frame(&mut writer))), 0);
+}
+
+// moves the real pointer back and forth and clicks F24, the net effect on the desktop is nothing
+#[test]
+fn virtual_device_doesnt_allocate_once_warmed_up() {
+    // `VirtualDevice::new` panics when the uinput module isn't loaded
+    if !Path::new("/dev/uinput").exists() {
+        eprintln!("skipped: /dev/uinput doesn't exist");
+        return;
+    }
+    let mut device = match VirtualDevice::default() {
+        Ok(device) => device,
+        Err(err) => {
+            eprintln!("skipped: {err}");
+            return;
+        }
+    };
+    let mut batch = Vec::new();
+
+    let mut frame = |device: &mut VirtualDevice, direction: Coord| {
+        batch.clear();
+        device.buffered_gradual_move_mouse_into(20 * direction, -7 * direction, &mut batch);
+        device.buffered_press_into(KEY_F24, &mut batch);
+        device.buffered_release_into(KEY_F24, &mut batch);
+        device.write_batch(&batch).unwrap();
+        device.move_mouse(3 * direction, direction).unwrap();
+        device.move_mouse_f(0.5 * direction as f64, 0.25 * direction as f64).unwrap();
+        device.emit(&[InputEvent::Rel(RelAxis::X, direction)]).unwrap();
+    };
+
+    frame(&mut device, 1);
+    frame(&mut device, -1);
+    let allocations = allocations_in(|| {
+        for idx in 0..100 {
+            frame(&mut device, if idx % 2 == 0 { 1 } else { -1 });
+        }
+    });
+    assert_eq!(allocations, 0);
+    // the counter does see the `Vec` of the wrappers
+    assert_eq!(allocations_in(|| drop(device.buffered_move_mouse_x(1))), 1);
+}